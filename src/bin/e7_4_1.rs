@@ -13,13 +13,14 @@ use rand::{Rng, SeedableRng};
 use rand_distr::{Bernoulli, Distribution, Exp, Poisson, Uniform};
 use rand_pcg::Pcg64Mcg;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 use stoc::{test_theory, Vector};
 
 const SEED: u128 = 1;
-const MAX_THREADS: u32 = 8;
 
 const TIME: f64 = 1000.;
 
+#[derive(Debug, Serialize)]
 struct Parameters;
 
 fn sample_lifetime(rng: &mut impl Rng) -> f64 {
@@ -52,13 +53,6 @@ fn theory(_parameters: &Parameters) -> f64 {
 fn main() {
     let mut rng = Pcg64Mcg::new(SEED);
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &Parameters,
-        1_000_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &Parameters, 1_000_000, &mut rng);
+    println!("{result}");
 }
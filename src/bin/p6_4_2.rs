@@ -12,11 +12,15 @@ use rand::{Rng, SeedableRng};
 use rand_distr::{Bernoulli, Distribution, Exp, Poisson, Uniform};
 use rand_pcg::Pcg64Mcg;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use stoc::{test_theory, Vector};
+use serde::Serialize;
+use stoc::{
+    test_theory, BirthDeathAnalysis, ContinuousMarkovProcess, MarkovQueueProbabilities,
+    StochasticProcess, Vector,
+};
 
 const SEED: u128 = 5;
-const MAX_THREADS: u32 = 8;
 
+#[derive(Debug, Serialize)]
 struct Parameters {
     lambda: f64,
     mu: f64,
@@ -25,68 +29,17 @@ struct Parameters {
     sample_end: f64,
 }
 
-fn advance_state(
-    parameters: &Parameters,
-    cur_state: &mut usize,
-    cur_time: &mut f64,
-    rng: &mut impl Rng,
-) -> f64 {
+fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> Vector {
     let &Parameters {
         lambda,
         mu,
-        n: _,
-        sample_start: _,
-        sample_end: _,
-    } = parameters;
-    if *cur_state == 0 {
-        let birth_time = Exp::new(lambda).unwrap().sample(rng);
-        *cur_time += birth_time;
-        *cur_state += 1;
-        birth_time
-    } else {
-        let sojourn_time = Exp::new(lambda + mu).unwrap().sample(rng);
-        let death = Bernoulli::new(mu / (lambda + mu)).unwrap().sample(rng);
-        *cur_time += sojourn_time;
-        if death {
-            *cur_state -= 1;
-        } else {
-            *cur_state += 1;
-        }
-        sojourn_time
-    }
-}
-
-fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> Vector {
-    let &Parameters {
-        lambda: _,
-        mu: _,
         n,
         sample_start,
         sample_end,
     } = parameters;
-    let mut cur_time = 0.;
-    let mut cur_state = 0usize;
-    let mut prev_state = 0usize;
-    while cur_time <= sample_start {
-        prev_state = cur_state;
-        advance_state(parameters, &mut cur_state, &mut cur_time, rng);
-    }
-    let mut time_in_states = Vector::from_elem(n, 0.);
-    if prev_state < n {
-        time_in_states[prev_state] += cur_time - sample_start;
-    }
-
-    while cur_time <= sample_end {
-        prev_state = cur_state;
-        let time = advance_state(parameters, &mut cur_state, &mut cur_time, rng);
-        if prev_state < n {
-            time_in_states[prev_state] += time;
-        }
-    }
-    if prev_state < n {
-        time_in_states[prev_state] -= cur_time - sample_end;
-    }
-    time_in_states / (sample_end - sample_start)
+    let mut process = ContinuousMarkovProcess::new(MarkovQueueProbabilities::new(lambda, mu, 1), 0);
+    let occupation = process.occupation(sample_start, sample_end, rng);
+    Vector::from_shape_fn(n, |k| occupation.get(&(k as u64)).copied().unwrap_or(0.))
 }
 
 fn theory(parameters: &Parameters) -> Vector {
@@ -97,9 +50,10 @@ fn theory(parameters: &Parameters) -> Vector {
         sample_start: _,
         sample_end: _,
     } = parameters;
-    let theta = lambda / mu;
-    let pi_0 = 1. - theta;
-    Vector::from_shape_fn(n, |k| pi_0 * theta.powi(k as i32))
+    let analysis =
+        BirthDeathAnalysis::infinite(&MarkovQueueProbabilities::new(lambda, mu, 1), 1e-12)
+            .expect("Queue must be stable");
+    Vector::from_shape_fn(n, |k| analysis.probability(k as u64))
 }
 
 fn main() {
@@ -114,13 +68,6 @@ fn main() {
     };
 
     assert!(parameters.lambda < parameters.mu);
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        100_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 100_000, &mut rng);
+    println!("{result}");
 }
@@ -12,11 +12,15 @@ use rand::{Rng, SeedableRng};
 use rand_distr::{Bernoulli, Distribution, Exp, Poisson, Uniform};
 use rand_pcg::Pcg64Mcg;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use stoc::{test_theory, Vector};
+use serde::Serialize;
+use stoc::{
+    test_theory, BirthDeathAnalysis, ContinuousMarkovProcess, FinitePopulationProbabilities,
+    StochasticProcess, Vector,
+};
 
 const SEED: u128 = 5;
-const MAX_THREADS: u32 = 8;
 
+#[derive(Debug, Serialize)]
 struct Parameters {
     alpha: f64,
     beta: f64,
@@ -25,59 +29,24 @@ struct Parameters {
     sample_end: f64,
 }
 
-fn advance_state(
-    parameters: &Parameters,
-    cur_state: &mut usize,
-    cur_time: &mut f64,
-    rng: &mut impl Rng,
-) -> f64 {
-    let &Parameters {
-        alpha,
-        beta,
-        n,
-        sample_start: _,
-        sample_end: _,
-    } = parameters;
-    let lambda = alpha * ((n - *cur_state) as f64);
-    let mu = beta * (*cur_state as f64);
-    let sojourn_time = Exp::new(lambda + mu).unwrap().sample(rng);
-    let death = Bernoulli::new(mu / (lambda + mu)).unwrap().sample(rng);
-    *cur_time += sojourn_time;
-    if death {
-        *cur_state -= 1;
-    } else {
-        *cur_state += 1;
-    }
-    sojourn_time
+/// Each of the `n` machines breaks down at rate `alpha` and is repaired at rate `beta`.
+fn repairs(alpha: f64, beta: f64, n: usize) -> FinitePopulationProbabilities {
+    FinitePopulationProbabilities::new(n as u64, alpha, beta, n as u64)
 }
 
 fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> Vector {
     let &Parameters {
-        alpha: _,
-        beta: _,
+        alpha,
+        beta,
         n,
         sample_start,
         sample_end,
     } = parameters;
-    let mut cur_time = 0.;
-    let mut cur_state = 0;
-    let mut prev_state = 0;
-    while cur_time < sample_start {
-        prev_state = cur_state;
-        advance_state(parameters, &mut cur_state, &mut cur_time, rng);
-    }
-
-    let mut time_in_states = Vector::from_elem(n + 1, 0.);
-    time_in_states[cur_state] += cur_time - sample_start;
-
-    while cur_time < sample_end {
-        prev_state = cur_state;
-        let time = advance_state(parameters, &mut cur_state, &mut cur_time, rng);
-        time_in_states[prev_state] += time;
-    }
-    time_in_states[prev_state] -= cur_time - sample_end;
-
-    time_in_states / (sample_end - sample_start)
+    let mut process = ContinuousMarkovProcess::new(repairs(alpha, beta, n), 0);
+    let occupation = process.occupation(sample_start, sample_end, rng);
+    Vector::from_shape_fn(n + 1, |k| {
+        occupation.get(&(k as u64)).copied().unwrap_or(0.)
+    })
 }
 
 fn theory(parameters: &Parameters) -> Vector {
@@ -88,15 +57,9 @@ fn theory(parameters: &Parameters) -> Vector {
         sample_start: _,
         sample_end: _,
     } = parameters;
-    let pi_0 = 1. / (1. + alpha / beta).powi(n as i32);
-
-    let mut pi = Vector::from_elem(n + 1, 0.);
-    pi[0] = pi_0;
-    for k in 1..=n {
-        pi[k] = pi[k - 1] * (alpha / beta) * (n - k + 1) as f64 / k as f64;
-    }
-
-    pi
+    BirthDeathAnalysis::finite(&repairs(alpha, beta, n), n + 1)
+        .distribution()
+        .clone()
 }
 
 fn main() {
@@ -111,13 +74,6 @@ fn main() {
     };
 
     assert!(parameters.sample_start < parameters.sample_end);
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        1_000_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 1_000_000, &mut rng);
+    println!("{result}");
 }
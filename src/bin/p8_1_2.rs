@@ -13,11 +13,12 @@ use rand::{Rng, SeedableRng};
 use rand_distr::{Bernoulli, Distribution, Exp, Normal, Poisson, Uniform};
 use rand_pcg::Pcg64Mcg;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 use stoc::{test_theory, Vector};
 
 const SEED: u128 = 1;
-const MAX_THREADS: u32 = 8;
 
+#[derive(Debug, Serialize)]
 struct Parameters {
     lambda: f64,
     t: f64,
@@ -55,13 +56,6 @@ fn main() {
         num_samples: 1_000,
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        100_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 100_000, &mut rng);
+    println!("{result}");
 }
@@ -14,11 +14,12 @@ use rand::{Rng, SeedableRng};
 use rand_distr::{Bernoulli, Distribution, Exp, Normal, Poisson, Uniform};
 use rand_pcg::Pcg64Mcg;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 use stoc::{test_theory, Vector};
 
 const SEED: u128 = 1;
-const MAX_THREADS: u32 = 8;
 
+#[derive(Debug, Serialize)]
 struct Parameters {
     a: f64,
     b: f64,
@@ -61,15 +62,8 @@ fn main() {
     assert!(parameters.b > 0.);
     assert!(parameters.step_size > 0.);
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        100_000,
-        MAX_THREADS,
-        &mut rng,
-    );
+    let result = test_theory(experiment, theory, &parameters, 100_000, &mut rng);
     let (&theory, &empirical) = result.parts();
     println!("ratio: {}", theory / empirical);
-    println!("{result:?}");
+    println!("{result}");
 }
@@ -13,11 +13,15 @@ use rand::{Rng, SeedableRng};
 use rand_distr::{Bernoulli, Distribution, Exp, Poisson, Uniform};
 use rand_pcg::Pcg64Mcg;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use stoc::{test_theory, Vector};
+use serde::Serialize;
+use stoc::{
+    sweep, test_theory, BirthDeathAnalysis, GeneralQueueSystem, MarkovQueueProbabilities,
+    QueueSystem, StochasticProcess, Vector,
+};
 
 const SEED: u128 = 1;
-const MAX_THREADS: u32 = 8;
 
+#[derive(Debug, Serialize)]
 struct Parameters {
     lambda: f64,
     nu: f64,
@@ -26,75 +30,41 @@ struct Parameters {
     sample_end: f64,
 }
 
-fn advance_state(
-    parameters: &Parameters,
-    cur_state: &mut usize,
-    cur_time: &mut f64,
-    rng: &mut impl Rng,
-) -> f64 {
+fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
     let &Parameters {
         lambda,
         nu,
         servers,
-        sample_start: _,
-        sample_end: _,
-    } = parameters;
-
-    let mu = nu * (servers.min(*cur_state as u32) as f64);
-    let sojourn_time = Exp::new(lambda + mu).unwrap().sample(rng);
-    let death = Bernoulli::new(mu / (lambda + mu)).unwrap().sample(rng);
-    *cur_time += sojourn_time;
-    if death {
-        *cur_state -= 1;
-    } else {
-        *cur_state += 1;
-    }
-    sojourn_time
-}
-
-fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
-    let &Parameters {
-        lambda: _,
-        nu: _,
-        servers: _,
         sample_start,
         sample_end,
     } = parameters;
-    let mut cur_time = 0.;
-    let mut cur_state = 0;
-    let mut prev_state;
-
-    let mut times = VecDeque::new();
-
-    let mut total = 0.;
-    let mut total_customers = 0;
-
-    while cur_time < sample_start {
-        prev_state = cur_state;
-        advance_state(parameters, &mut cur_state, &mut cur_time, rng);
-        if cur_state > prev_state {
-            times.push_back(cur_time);
-        } else {
-            times.pop_front().unwrap();
-        }
-        assert_eq!(times.len(), cur_state);
+    let mut queue = GeneralQueueSystem::new(
+        Some(servers as u64),
+        Exp::new(lambda).unwrap(),
+        Exp::new(nu).unwrap(),
+        0,
+        rng,
+    )
+    .with_event_log();
+    queue.advance_to(sample_end, rng);
+    // Let every customer that arrived in the window depart, so long stays are not left out.
+    while queue
+        .customers()
+        .next()
+        .is_some_and(|customer| customer.arrival_time() < sample_end)
+    {
+        queue.step(rng);
     }
 
-    while cur_time < sample_end {
-        prev_state = cur_state;
-        advance_state(parameters, &mut cur_state, &mut cur_time, rng);
-        if cur_state > prev_state {
-            times.push_back(cur_time);
-        } else {
-            total_customers += 1;
-            let waiting_time = cur_time - times.pop_front().unwrap();
-            total += waiting_time;
-        }
-        assert_eq!(times.len(), cur_state);
-    }
-    total / (total_customers as f64)
+    let event_log = queue.event_log().unwrap();
+    let sojourn_times: Vec<f64> = event_log
+        .departures_arrived_in(sample_start, sample_end)
+        .map(|customer| customer.sojourn_time().unwrap())
+        .collect();
+    sojourn_times.iter().sum::<f64>() / sojourn_times.len() as f64
 }
 
+/// Mean time in the system by Little's law.
 fn theory(parameters: &Parameters) -> f64 {
     let &Parameters {
         lambda,
@@ -103,41 +73,30 @@ fn theory(parameters: &Parameters) -> f64 {
         sample_start: _,
         sample_end: _,
     } = parameters;
-
-    if servers == 1 {
-        1. / (nu - lambda)
-    } else if servers == 2 {
-        let pi_0 = 1.
-            / (1. + (lambda / nu) + lambda.powi(2) / (2. * nu.powi(2) * (1. - lambda / (2. * nu))));
-        let l_0 = pi_0 / 2. * (lambda / nu).powi(2) * lambda
-            / (2. * nu * (1. - lambda / (2. * nu)).powi(2));
-        let _w_0 = l_0 / lambda;
-        let w = l_0 + 1. / nu;
-        w
-    } else {
-        unreachable!()
-    }
+    let queue = MarkovQueueProbabilities::new(lambda, nu, servers as u64);
+    let analysis = BirthDeathAnalysis::infinite(&queue, 1e-12).expect("Queue must be stable");
+    analysis.mean() / lambda
 }
 
 fn main() {
     let mut rng = Pcg64Mcg::new(SEED);
 
-    let parameters = Parameters {
-        lambda: 1.2,
+    let parameters = |&lambda: &f64| Parameters {
+        lambda,
         nu: 2.,
         servers: 2,
         sample_start: 100.,
         sample_end: 200.,
     };
 
-    assert!(parameters.sample_start < parameters.sample_end);
-    let result = test_theory(
+    let result = sweep(
         experiment,
         theory,
-        &parameters,
+        "lambda",
+        [0.4, 0.8, 1.2, 1.6],
+        parameters,
         1_000_000,
-        MAX_THREADS,
         &mut rng,
     );
-    println!("{result:?}");
+    println!("{result}");
 }
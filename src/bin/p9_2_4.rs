@@ -13,11 +13,15 @@ use rand::{Rng, SeedableRng};
 use rand_distr::{Bernoulli, Distribution, Exp, Poisson, Uniform};
 use rand_pcg::Pcg64Mcg;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use stoc::{test_theory, Vector};
+use serde::Serialize;
+use stoc::{
+    test_theory, BirthDeathAnalysis, ContinuousMarkovProcess, MarkovQueueProbabilities, Record,
+    StochasticProcess, Vector,
+};
 
 const SEED: u128 = 1;
-const MAX_THREADS: u32 = 8;
 
+#[derive(Debug, Serialize)]
 struct Parameters {
     lambda: f64,
     nu: f64,
@@ -26,77 +30,32 @@ struct Parameters {
     sample_end: f64,
 }
 
-fn advance_state(
-    parameters: &Parameters,
-    cur_state: &mut u64,
-    cur_time: &mut f64,
-    rng: &mut impl Rng,
-) -> f64 {
+fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> Record<2> {
     let &Parameters {
         lambda,
         nu,
         capacity,
-        sample_start: _,
-        sample_end: _,
-    } = parameters;
-
-    let mu = if *cur_state > 0 { nu } else { 0. };
-    let lambda = if *cur_state < capacity { lambda } else { 0. };
-    let sojourn_time = Exp::new(lambda + mu).unwrap().sample(rng);
-    let death = Bernoulli::new(mu / (lambda + mu)).unwrap().sample(rng);
-    *cur_time += sojourn_time;
-    if death {
-        *cur_state -= 1;
-    } else {
-        *cur_state += 1;
-    }
-    sojourn_time
-}
-
-fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> Vector {
-    let &Parameters {
-        lambda: _,
-        nu: _,
-        capacity,
         sample_start,
         sample_end,
     } = parameters;
-    let mut cur_time = 0.;
-    let mut cur_state = 0;
-
-    let mut idle_time = 0.;
-    let mut cap_time = 0.;
-
-    while cur_time < sample_start {
-        advance_state(parameters, &mut cur_state, &mut cur_time, rng);
-    }
-
-    if cur_state == 0 {
-        idle_time += cur_time - sample_start;
-    }
-    if cur_state == capacity {
-        cap_time += cur_time - sample_start;
-    }
-
-    while cur_time < sample_end {
-        let time = advance_state(parameters, &mut cur_state, &mut cur_time, rng);
-        if cur_state == 0 {
-            idle_time += time;
-        }
-        if cur_state > capacity {
-            cap_time += time;
-        }
-    }
-    if cur_state == 0 {
-        idle_time -= cur_time - sample_end;
-    }
-    if cur_state == capacity {
-        cap_time -= cur_time - sample_end;
-    }
-    Vector::from_shape_vec(2, vec![idle_time, cap_time]).unwrap() / (sample_end - sample_start)
+    let mut process = ContinuousMarkovProcess::new(
+        MarkovQueueProbabilities::with_capacity(lambda, nu, 1, capacity),
+        0,
+    );
+    process.time_average(
+        |state| {
+            Record::new([
+                ("idle", if state == 0 { 1. } else { 0. }),
+                ("at capacity", if state == capacity { 1. } else { 0. }),
+            ])
+        },
+        sample_start,
+        sample_end,
+        rng,
+    )
 }
 
-fn theory(parameters: &Parameters) -> Vector {
+fn theory(parameters: &Parameters) -> Record<2> {
     let &Parameters {
         lambda,
         nu,
@@ -104,15 +63,12 @@ fn theory(parameters: &Parameters) -> Vector {
         sample_start: _,
         sample_end: _,
     } = parameters;
-
-    let rho = lambda / nu;
-    let mut total = 0.;
-    let mut power = 1.;
-    for _ in 0..=capacity {
-        total += power;
-        power *= rho;
-    }
-    Vector::from_shape_vec(2, vec![1. / total, rho.powi(capacity as i32) / total]).unwrap()
+    let queue = MarkovQueueProbabilities::with_capacity(lambda, nu, 1, capacity);
+    let analysis = BirthDeathAnalysis::finite(&queue, capacity as usize + 1);
+    Record::new([
+        ("idle", analysis.probability(0)),
+        ("at capacity", analysis.probability(capacity)),
+    ])
 }
 
 fn main() {
@@ -127,13 +83,6 @@ fn main() {
     };
 
     assert!(parameters.sample_start < parameters.sample_end);
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        1_000_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 1_000_000, &mut rng);
+    println!("{result}");
 }
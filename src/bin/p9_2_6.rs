@@ -13,11 +13,15 @@ use rand::{Rng, SeedableRng};
 use rand_distr::{Bernoulli, Distribution, Exp, Poisson, Uniform};
 use rand_pcg::Pcg64Mcg;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use stoc::{test_theory, Vector};
+use serde::Serialize;
+use stoc::{
+    test_theory, BirthDeathAnalysis, ContinuousMarkovProcess, MarkovQueueProbabilities,
+    StochasticProcess, Vector,
+};
 
 const SEED: u128 = 1;
-const MAX_THREADS: u32 = 8;
 
+#[derive(Debug, Serialize)]
 struct Parameters {
     lambda: f64,
     nu: f64,
@@ -26,63 +30,21 @@ struct Parameters {
     sample_end: f64,
 }
 
-fn advance_state(
-    parameters: &Parameters,
-    cur_state: &mut u64,
-    cur_time: &mut f64,
-    rng: &mut impl Rng,
-) -> f64 {
+fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
     let &Parameters {
         lambda,
         nu,
-        capacity: _,
-        sample_start: _,
-        sample_end: _,
-    } = parameters;
-
-    let mu = if *cur_state > 0 { nu } else { 0. };
-    let sojourn_time = Exp::new(lambda + mu).unwrap().sample(rng);
-    let death = Bernoulli::new(mu / (lambda + mu)).unwrap().sample(rng);
-    *cur_time += sojourn_time;
-    if death {
-        *cur_state -= 1;
-    } else {
-        *cur_state += 1;
-    }
-    sojourn_time
-}
-
-fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
-    let &Parameters {
-        lambda: _,
-        nu: _,
         capacity,
         sample_start,
         sample_end,
     } = parameters;
-    let mut cur_time = 0.;
-    let mut cur_state = 0;
-
-    let mut total = 0.;
-
-    while cur_time < sample_start {
-        advance_state(parameters, &mut cur_state, &mut cur_time, rng);
-    }
-
-    if cur_state > capacity {
-        total += cur_time - sample_start;
-    }
-
-    while cur_time < sample_end {
-        let time = advance_state(parameters, &mut cur_state, &mut cur_time, rng);
-        if cur_state > capacity {
-            total += time;
-        }
-    }
-    if cur_state > capacity {
-        total -= cur_time - sample_end;
-    }
-    total / (sample_end - sample_start)
+    let mut process = ContinuousMarkovProcess::new(MarkovQueueProbabilities::new(lambda, nu, 1), 0);
+    process.time_average(
+        |state| if state > capacity { 1. } else { 0. },
+        sample_start,
+        sample_end,
+        rng,
+    )
 }
 
 fn theory(parameters: &Parameters) -> f64 {
@@ -93,15 +55,10 @@ fn theory(parameters: &Parameters) -> f64 {
         sample_start: _,
         sample_end: _,
     } = parameters;
-
-    let rho = lambda / nu;
-    let mut total = 0.;
-    let mut power = 1.;
-    for _ in 0..=capacity {
-        total += power;
-        power *= rho;
-    }
-    1. - total * (1. - rho)
+    let analysis =
+        BirthDeathAnalysis::infinite(&MarkovQueueProbabilities::new(lambda, nu, 1), 1e-12)
+            .expect("Queue must be stable");
+    1. - (0..=capacity).map(|k| analysis.probability(k)).sum::<f64>()
 }
 
 fn main() {
@@ -116,13 +73,6 @@ fn main() {
     };
 
     assert!(parameters.sample_start < parameters.sample_end);
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        1_000_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 1_000_000, &mut rng);
+    println!("{result}");
 }
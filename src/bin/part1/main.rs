@@ -1,16 +1,16 @@
 use std::env;
 
-use rand::Rng;
-use rand_distr::{Poisson, Binomial, Distribution};
-use stoc::Matrix;
+use rand::RngCore;
+use rand_distr::{Binomial, Distribution, Poisson};
+use serde::Serialize;
+use stoc::{ClosureTransitions, DiscreteMarkovChain, Matrix};
 
 mod question2;
 mod question3;
 
 const SEED: u128 = 4;
-const MAX_THREADS: u32 = 8;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct ModelParameters {
     pub mu: f64,
     pub p: f64,
@@ -24,10 +24,10 @@ impl ModelParameters {
         (0..=(u64::min(k, l)))
             .map(|h| {
                 let binomial = (num_integer::binomial(k, h) as f64)
-                * (1.-p).powi(h as i32)
-                * p.powi((k - h) as i32);
-                let poisson = (((1.-p) * mu).powi((l - h) as i32) * f64::exp(-(1.-p) * mu))
-                / (stoc::factorial(l - h) as f64);
+                    * (1. - p).powi(h as i32)
+                    * p.powi((k - h) as i32);
+                let poisson = (((1. - p) * mu).powi((l - h) as i32) * f64::exp(-(1. - p) * mu))
+                    / (stoc::factorial(l - h) as f64);
                 binomial * poisson
             })
             .sum::<f64>()
@@ -64,41 +64,22 @@ impl Default for ModelParameters {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Process {
-    parameters: ModelParameters,
-    state: u64,
-    cur_day: u64,
-    failure_distribution: Poisson<f64>,
-}
-
-impl Process {
-    fn new(parameters: ModelParameters, start_state: u64) -> Self {
-        Self {
-            parameters,
-            state: start_state,
-            cur_day: 0,
-            failure_distribution: Poisson::new(parameters.mu*(1.-parameters.p)).unwrap(),
-        }
-
-    }
-
-    fn state(&self) -> u64 {
-        self.state
-    }
-
-    fn step(&mut self, rng: &mut impl Rng) {
-        let &mut Self { parameters, state, cur_day: _, failure_distribution } = self;
-        let &ModelParameters { mu: _, p, a: _ } = &parameters;
-    
-        let new_failures = failure_distribution.sample(rng) as u64;
-        let kept_failures = Binomial::new(state, 1.-p).unwrap().sample(rng);
-
-        self.state = new_failures + kept_failures;
-        self.cur_day += 1;
+impl ModelParameters {
+    /// The number of failures, simulated directly from the daily repairs and new failures rather
+    /// than from `transition_matrix`.
+    fn chain(
+        &self,
+        start_state: u64,
+    ) -> DiscreteMarkovChain<ClosureTransitions<impl Fn(u64, &mut dyn RngCore) -> u64>> {
+        let &Self { mu, p, a: _ } = self;
+        let failure_distribution = Poisson::new(mu * (1. - p)).unwrap();
+        let transitions = ClosureTransitions::new(move |state, rng: &mut dyn RngCore| {
+            let new_failures = failure_distribution.sample(rng) as u64;
+            let kept_failures = Binomial::new(state, 1. - p).unwrap().sample(rng);
+            new_failures + kept_failures
+        });
+        DiscreteMarkovChain::new(transitions, start_state)
     }
-
-
 }
 
 fn main() {
@@ -1,10 +1,11 @@
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
-use stoc::{test_theory, Vector};
+use serde::Serialize;
+use stoc::{test_theory, TransitionMatrix, Vector};
 
-use crate::{ModelParameters, MAX_THREADS, SEED, Process};
+use crate::{ModelParameters, SEED};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     start_state: u64,
@@ -19,9 +20,12 @@ fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
         days,
         target,
     } = parameters;
-    let mut process = Process::new(model_parameters, start_state);
+    let mut process = model_parameters.chain(start_state);
     for _ in 0..days {
         process.step(rng);
+        if process.state() >= model_parameters.a {
+            return 0.;
+        }
     }
 
     if process.state() == target {
@@ -38,18 +42,15 @@ fn theory(parameters: &Parameters) -> f64 {
         days,
         target,
     } = parameters;
-    let mut start = vec![0.; model_parameters.a as usize+1];
-    start[start_state as usize]= 1.;
-    let start = Vector::from_shape_vec(model_parameters.a as usize+1, start).unwrap();
-    let p = model_parameters.transition_matrix();
-    let p10 = (0..days).fold(start, |cur_p, _| cur_p.dot(&p));
-    dbg!(p10)[target as usize]
-    
+    let transitions = TransitionMatrix::new(model_parameters.transition_matrix());
+    let mut start = Vector::zeros(transitions.num_states());
+    start[start_state as usize] = 1.;
+    transitions.n_step_distribution(&start, days)[target as usize]
 }
 
 pub fn main() {
     let mut rng = Pcg64Mcg::new(SEED);
-        
+
     let parameters = Parameters {
         model_parameters: ModelParameters::default(),
         start_state: 0,
@@ -57,14 +58,7 @@ pub fn main() {
         target: 4,
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        1_000_000,
-        MAX_THREADS,
-        &mut rng,
-    );
+    let result = test_theory(experiment, theory, &parameters, 1_000_000, &mut rng);
 
-    println!("{result:?}");
+    println!("{result}");
 }
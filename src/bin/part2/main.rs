@@ -1,12 +1,15 @@
 use std::env;
 
-mod question9;
+use serde::Serialize;
+use stoc::Matrix;
+
 mod question10;
 mod question11;
+mod question9;
 
 const SEED: u128 = 0;
-const MAX_THREADS: u32 = 8;
 
+#[derive(Debug, Serialize)]
 pub struct ModelParameters {
     pub lambda1: f64,
     pub lambda2: f64,
@@ -17,7 +20,34 @@ pub struct ModelParameters {
 
 impl Default for ModelParameters {
     fn default() -> Self {
-        ModelParameters { lambda1: 1./33., lambda2: 10./33., p1: 1./11., p2: 10./11., mu: 1. }
+        ModelParameters {
+            lambda1: 1. / 33.,
+            lambda2: 10. / 33.,
+            p1: 1. / 11.,
+            p2: 10. / 11.,
+            mu: 1.,
+        }
+    }
+}
+
+impl ModelParameters {
+    /// States: 0 and 1 working with a type 1 or type 2 part, 2 and 3 the two stages of repair.
+    pub fn generator(&self) -> Matrix {
+        let &ModelParameters {
+            lambda1,
+            lambda2,
+            p1,
+            p2,
+            mu,
+        } = self;
+        #[rustfmt::skip]
+        let rates = vec![
+            -lambda1, 0., lambda1, 0.,
+            0., -lambda2, lambda2, 0.,
+            0., 0., -mu, mu,
+            mu * p1, mu * p2, 0., -mu,
+        ];
+        Matrix::from_shape_vec((4, 4), rates).unwrap()
     }
 }
 
@@ -1,10 +1,14 @@
 use rand::Rng;
 use rand_distr::{Bernoulli, Distribution};
 use rand_pcg::Pcg64Mcg;
-use stoc::{test_theory, ContinuousMarkovProcess, Matrix, MatrixTransitions};
+use serde::Serialize;
+use stoc::{
+    expected_accumulated_reward, test_theory, ContinuousMarkovProcess, MatrixTransitions, Vector,
+};
 
-use crate::{ModelParameters, MAX_THREADS, SEED};
+use crate::{ModelParameters, SEED};
 
+#[derive(Debug, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     time: f64,
@@ -12,40 +16,44 @@ struct Parameters {
 
 fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
     let &Parameters {
-        model_parameters:
-            ModelParameters {
-                lambda1,
-                lambda2,
-                p1,
-                p2,
-                mu,
-            },
+        model_parameters: ModelParameters { p1, .. },
         time,
     } = parameters;
 
-    let start_state = if Bernoulli::new(p1).unwrap().sample(rng) { 0} else {1};
+    let start_state = if Bernoulli::new(p1).unwrap().sample(rng) {
+        0
+    } else {
+        1
+    };
 
-    let transition_matrix = Matrix::from_shape_vec((4, 4), vec![
-        -lambda1, 0., lambda1, 0.,
-        0., -lambda2, lambda2, 0.,
-        0., 0., -mu, mu,
-        mu*p1, mu*p2, 0., -mu,
-    ]).unwrap();
-    let mut process = ContinuousMarkovProcess::new(MatrixTransitions::new(transition_matrix), start_state);
-    
-    let mut total_repairs = 0;
+    let transition_matrix = parameters.model_parameters.generator();
+    let mut process = ContinuousMarkovProcess::with_trajectory(
+        MatrixTransitions::new(transition_matrix),
+        start_state,
+    );
     while process.time() < time {
-        if process.state() == 0 || process.state() == 1 {
-            total_repairs += 1;
-        }
         process.step(rng);
     }
-    
-    (total_repairs - 1) as f64
+    let trajectory = process.trajectory().unwrap();
+
+    (trajectory.visits(|state| state == 0 || state == 1, 0., time) - 1) as f64
 }
 
-fn theory(_parameters: &Parameters) -> f64 {
-    5.752
+/// Repairs finish at rate `mu` while in state 3, the first counted repair ends the initial one.
+fn theory(parameters: &Parameters) -> f64 {
+    let &Parameters {
+        model_parameters: ModelParameters { p1, p2, mu, .. },
+        time,
+    } = parameters;
+    let initial = Vector::from_vec(vec![p1, p2, 0., 0.]);
+    let repair_rate = Vector::from_vec(vec![0., 0., 0., mu]);
+    expected_accumulated_reward(
+        &parameters.model_parameters.generator(),
+        &initial,
+        &repair_rate,
+        time,
+        1e-12,
+    )
 }
 
 pub fn main() {
@@ -56,13 +64,6 @@ pub fn main() {
         time: 40.,
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        10_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 10_000, &mut rng);
+    println!("{result}");
 }
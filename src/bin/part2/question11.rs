@@ -1,10 +1,16 @@
+use ndarray::{s, Axis};
 use rand::Rng;
 use rand_distr::{Bernoulli, Distribution};
 use rand_pcg::Pcg64Mcg;
-use stoc::{test_theory, ContinuousMarkovProcess, Matrix, MatrixTransitions};
+use serde::Serialize;
+use stoc::{
+    deviation_matrix, expm, stationary_distribution, test_theory, ContinuousMarkovProcess, Matrix,
+    MatrixTransitions,
+};
 
-use crate::{ModelParameters, MAX_THREADS, SEED};
+use crate::{ModelParameters, SEED};
 
+#[derive(Debug, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     min_run_time: f64,
@@ -14,14 +20,7 @@ struct Parameters {
 
 fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
     let &Parameters {
-        model_parameters:
-            ModelParameters {
-                lambda1,
-                lambda2,
-                p1,
-                p2,
-                mu,
-            },
+        model_parameters: ModelParameters { p1, mu, .. },
         min_run_time,
         max_run_time,
         time,
@@ -29,40 +28,64 @@ fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
 
     let sample_time = rng.gen_range(min_run_time..max_run_time);
 
-    let start_state = if Bernoulli::new(p1).unwrap().sample(rng) { 0 } else { 1 };
+    let start_state = if Bernoulli::new(p1).unwrap().sample(rng) {
+        0
+    } else {
+        1
+    };
+
+    let transition_matrix = parameters.model_parameters.generator();
+    let repair_rate = stationary_distribution(&transition_matrix)[3] * mu;
+    let expected_repairs = repair_rate * time;
+    let mut process = ContinuousMarkovProcess::with_trajectory(
+        MatrixTransitions::new(transition_matrix),
+        start_state,
+    );
 
-    let transition_matrix = Matrix::from_shape_vec((4, 4), vec![
-        -lambda1, 0., lambda1, 0.,
-        0., -lambda2, lambda2, 0.,
-        0., 0., -mu, mu,
-        mu*p1, mu*p2, 0., -mu,
-    ]).unwrap();
-    let mut process = ContinuousMarkovProcess::new(MatrixTransitions::new(transition_matrix), start_state);
-    
     /*process.step(rng);
     while process.state() != 0 && process.state() != 1 {
         process.step(rng);
     }
     (process.time()-8.)*(process.time()-8.)*/
-    
-    while process.time() < sample_time {
-        process.step(rng);
-    }
 
-    let mut total_repairs = 0;
-    
     while process.time() < sample_time + time {
-        if process.state() == 0 || process.state() == 1 {
-            total_repairs += 1;
-        }
         process.step(rng);
     }
-    
-    (total_repairs as f64 - 25.)*(total_repairs as f64 - 25.)
+    let trajectory = process.trajectory().unwrap();
+
+    let total_repairs = trajectory.visits(
+        |state| state == 0 || state == 1,
+        sample_time,
+        sample_time + time,
+    );
+
+    (total_repairs as f64 - expected_repairs) * (total_repairs as f64 - expected_repairs)
 }
 
-fn theory(_parameters: &Parameters) -> f64 {
-    0.041
+/// Variance of the number of repairs in a window of length `t` of the stationary chain,
+/// `λ̄t + 2 π Q₁ (t D - (I - e^{Qt}) D²) Q₁ 1`, where `Q₁` holds the rates of the counted
+/// transitions out of the last repair stage and `D` is the deviation matrix.
+fn theory(parameters: &Parameters) -> f64 {
+    let generator = parameters.model_parameters.generator();
+    let n = generator.nrows();
+    let distribution = stationary_distribution(&generator);
+    let deviation = deviation_matrix(&generator);
+
+    let mut repairs = Matrix::zeros((n, n));
+    repairs
+        .slice_mut(s![3, ..2])
+        .assign(&generator.slice(s![3, ..2]));
+    let repair_rates = repairs.sum_axis(Axis(1));
+    let repair_rate = distribution.dot(&repair_rates);
+
+    let t = parameters.time;
+    let decay = Matrix::eye(n) - expm(&(&generator * t));
+    let correlation = &deviation * t - decay.dot(&deviation).dot(&deviation);
+    repair_rate * t
+        + 2. * distribution
+            .dot(&repairs)
+            .dot(&correlation)
+            .dot(&repair_rates)
 }
 
 pub fn main() {
@@ -75,13 +98,6 @@ pub fn main() {
         time: 200.,
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        100_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 100_000, &mut rng);
+    println!("{result}");
 }
@@ -1,10 +1,15 @@
 use rand::Rng;
 use rand_distr::{Bernoulli, Distribution};
 use rand_pcg::Pcg64Mcg;
-use stoc::{test_theory, ContinuousMarkovProcess, Matrix, MatrixTransitions};
+use serde::Serialize;
+use stoc::{
+    stationary_distribution, test_theory, transient_distribution, ContinuousMarkovProcess,
+    MatrixTransitions,
+};
 
-use crate::{ModelParameters, MAX_THREADS, SEED};
+use crate::{ModelParameters, SEED};
 
+#[derive(Debug, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     min_run_time: f64,
@@ -14,14 +19,7 @@ struct Parameters {
 
 fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
     let &Parameters {
-        model_parameters:
-            ModelParameters {
-                lambda1,
-                lambda2,
-                p1,
-                p2,
-                mu,
-            },
+        model_parameters: ModelParameters { p1, .. },
         min_run_time,
         max_run_time,
         time,
@@ -29,33 +27,40 @@ fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
 
     let sample_time = rng.gen_range(min_run_time..max_run_time);
 
-    let start_state = if Bernoulli::new(p1).unwrap().sample(rng) { 0} else {1};
+    let start_state = if Bernoulli::new(p1).unwrap().sample(rng) {
+        0
+    } else {
+        1
+    };
+
+    let transition_matrix = parameters.model_parameters.generator();
+    let mut process =
+        ContinuousMarkovProcess::new(MatrixTransitions::new(transition_matrix), start_state);
 
-    let transition_matrix = Matrix::from_shape_vec((4, 4), vec![
-        -lambda1, 0., lambda1, 0.,
-        0., -lambda2, lambda2, 0.,
-        0., 0., -mu, mu,
-        mu*p1, mu*p2, 0., -mu,
-    ]).unwrap();
-    let mut process = ContinuousMarkovProcess::new(MatrixTransitions::new(transition_matrix), start_state);
-    
     while process.time() < sample_time {
         process.step(rng);
     }
-    
+
     while process.state() != 0 && process.state() != 1 {
         process.step(rng);
     }
-    
+
     if process.time() - sample_time > time {
-        1. 
+        1.
     } else {
         0.
     }
 }
 
-fn theory(_parameters: &Parameters) -> f64 {
-    0.381
+/// From the stationary distribution, the probability that the process does not jump into state 0
+/// or 1 within `time`, i.e. that the chain with those jumps removed survives.
+fn theory(parameters: &Parameters) -> f64 {
+    let generator = parameters.model_parameters.generator();
+    let stationary = stationary_distribution(&generator);
+    let mut killed = generator;
+    killed[[3, 0]] = 0.;
+    killed[[3, 1]] = 0.;
+    transient_distribution(&killed, &stationary, parameters.time, 1e-12).sum()
 }
 
 pub fn main() {
@@ -68,13 +73,6 @@ pub fn main() {
         time: 8.,
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        10_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 10_000, &mut rng);
+    println!("{result}");
 }
@@ -1,5 +1,8 @@
 use std::env;
 
+use serde::Serialize;
+use stoc::{BirthAndDeathProbabilities, MarkovQueueProbabilities, Matrix};
+
 mod question13;
 mod question14;
 mod question15;
@@ -7,9 +10,8 @@ mod question16;
 mod question18;
 
 const SEED: u128 = 0;
-const MAX_THREADS: u32 = 8;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct ModelParameters {
     pub units: u64,
     pub failure_rate: f64,
@@ -17,44 +19,25 @@ pub struct ModelParameters {
     pub service_startup_time: f64,
 }
 
-/*
-pub struct SingleUnitSystem {
-    start_up_time: f64,
-    service_rate: f64,
-    failure_rate: f64,
-    cur_startup_time: f64,
-    cur_time: f64,
-    cur_state: u64,
-}
-
-impl SingleUnitSystem {
-    pub fn new(
-        start_up_time: f64,
-        service_rate: f64,
-        failure_rate: f64,
-        start_state: u64,
-    ) -> Self {
-        assert!(start_up_time >= 0.);
-        assert!(service_rate >= 0.);
-        assert!(failure_rate >= 0.);
-        Self {
-            start_up_time,
-            service_rate,
-            failure_rate,
-            cur_startup_time: start_up_time,
-            cur_time: 0.,
-            cur_state: start_state,
+impl ModelParameters {
+    /// Generator of the number of failed units on the states up to `critical_value`, which is made
+    /// absorbing.
+    pub fn generator(&self, critical_value: u64) -> Matrix {
+        let probabilities =
+            MarkovQueueProbabilities::new(self.failure_rate, 1. / self.service_time, self.units);
+        let num_states = critical_value as usize + 1;
+        let mut generator = Matrix::zeros((num_states, num_states));
+        for k in 0..critical_value as usize {
+            let (birth_rate, death_rate) = probabilities.probability_tuple(k as u64);
+            generator[[k, k + 1]] = birth_rate;
+            if k > 0 {
+                generator[[k, k - 1]] = death_rate;
+            }
+            generator[[k, k]] = -birth_rate - if k > 0 { death_rate } else { 0. };
         }
+        generator
     }
-
-    pub fn time(&self) -> f64 {
-        self.cur_time
-    }
-
-    pub fn state(&self) -> u64 {
-        self.cur_state
-    }
-}*/
+}
 
 fn main() {
     let arg = env::args().nth(1).expect("No question number given.");
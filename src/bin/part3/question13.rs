@@ -1,12 +1,13 @@
 use rand::Rng;
 use rand_distr::{Distribution, Exp};
 use rand_pcg::Pcg64Mcg;
+use serde::Serialize;
 use statrs::distribution::Erlang;
-use stoc::{test_theory, MarkovQueueProbabilities, ContinuousMarkovProcess};
+use stoc::{test_theory, ContinuousMarkovProcess, MarkovQueueProbabilities};
 
-use crate::{ModelParameters, MAX_THREADS, SEED};
+use crate::{ModelParameters, SEED};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     min_run_time: f64,
@@ -17,14 +18,14 @@ struct Parameters {
 }
 
 fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
-
     let &Parameters {
-        model_parameters: ModelParameters {
-            units,
-            failure_rate,
-            service_time,
-            service_startup_time,
-        },
+        model_parameters:
+            ModelParameters {
+                units,
+                failure_rate,
+                service_time,
+                service_startup_time,
+            },
         min_run_time,
         max_run_time,
         start_state,
@@ -34,32 +35,30 @@ fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
 
     assert_eq!(service_startup_time, 0.);
 
-    let markov_queue_probabilities = MarkovQueueProbabilities::new(failure_rate, 1./service_time, units);
+    let markov_queue_probabilities =
+        MarkovQueueProbabilities::new(failure_rate, 1. / service_time, units);
     let sample_time = rng.gen_range(min_run_time..max_run_time);
     let mut process = ContinuousMarkovProcess::new(markov_queue_probabilities, start_state);
-    let mut old_state = process.state();
-    while process.time() < sample_time {
-        old_state = process.state();
-        process.step(rng);
-    }
-    let queue_length = old_state;
+    let queue_length = process.run_until_time(sample_time, rng);
 
     let total_time = if use_erlang {
         let wait_time = if queue_length < units {
             0.
         } else {
-            Erlang::new(queue_length-units+1, units as f64/service_time).unwrap().sample(rng)
+            Erlang::new(queue_length - units + 1, units as f64 / service_time)
+                .unwrap()
+                .sample(rng)
         };
-        let repair_time = Exp::new(1./service_time).unwrap().sample(rng);
+        let repair_time = Exp::new(1. / service_time).unwrap().sample(rng);
         wait_time + repair_time
     } else {
         let mut queue_length = queue_length;
         let mut total_time = 0.;
         while queue_length >= 9 {
-            total_time += Exp::new(units as f64/service_time).unwrap().sample(rng);
+            total_time += Exp::new(units as f64 / service_time).unwrap().sample(rng);
             queue_length -= 1;
         }
-        total_time += Exp::new(1./service_time).unwrap().sample(rng);
+        total_time += Exp::new(1. / service_time).unwrap().sample(rng);
         total_time
     };
     if total_time > time {
@@ -90,13 +89,6 @@ pub fn main() {
         use_erlang: true,
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        50_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 50_000, &mut rng);
+    println!("{result}");
 }
@@ -1,10 +1,14 @@
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
-use stoc::{test_theory, MarkovQueueProbabilities, ContinuousMarkovProcess};
+use serde::Serialize;
+use stoc::{
+    expected_hitting_times, test_theory, ChainMatrix, ContinuousMarkovProcess,
+    MarkovQueueProbabilities,
+};
 
-use crate::{ModelParameters, MAX_THREADS, SEED};
+use crate::{ModelParameters, SEED};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     critical_value: u64,
@@ -13,27 +17,37 @@ struct Parameters {
 
 fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
     let &Parameters {
-        model_parameters: ModelParameters {
-            units,
-            failure_rate,
-            service_time,
-            service_startup_time,
-        },
+        model_parameters:
+            ModelParameters {
+                units,
+                failure_rate,
+                service_time,
+                service_startup_time,
+            },
         critical_value,
         start_state,
     } = parameters;
     assert_eq!(service_startup_time, 0.);
 
-    let markov_queue_probabilities = MarkovQueueProbabilities::new(failure_rate, 1./service_time, units);
+    let markov_queue_probabilities =
+        MarkovQueueProbabilities::new(failure_rate, 1. / service_time, units);
     let mut process = ContinuousMarkovProcess::new(markov_queue_probabilities, start_state);
-    while process.state() < critical_value {
-        process.step(rng);
-    }
-    process.time()
+    process
+        .run_until_hit(|state| state >= critical_value, rng)
+        .unwrap()
 }
 
-fn theory(_parameters: &Parameters) -> f64 {
-    6.14
+fn theory(parameters: &Parameters) -> f64 {
+    let &Parameters {
+        model_parameters,
+        critical_value,
+        start_state,
+    } = parameters;
+    let generator = model_parameters.generator(critical_value);
+    expected_hitting_times(
+        ChainMatrix::Generator(&generator),
+        &[critical_value as usize],
+    )[start_state as usize]
 }
 
 pub fn main() {
@@ -50,13 +64,6 @@ pub fn main() {
         start_state: 0,
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        100_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 100_000, &mut rng);
+    println!("{result}");
 }
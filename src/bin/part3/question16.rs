@@ -1,10 +1,14 @@
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
-use stoc::{test_theory, MarkovQueueProbabilities, ContinuousMarkovProcess};
+use serde::Serialize;
+use stoc::{
+    test_theory, transient_distribution, ContinuousMarkovProcess, MarkovQueueProbabilities,
+    StopReason, Vector,
+};
 
-use crate::{ModelParameters, MAX_THREADS, SEED};
+use crate::{ModelParameters, SEED};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     critical_value: u64,
@@ -14,31 +18,41 @@ struct Parameters {
 
 fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
     let &Parameters {
-        model_parameters: ModelParameters {
-            units,
-            failure_rate,
-            service_time,
-            service_startup_time,
-        },
+        model_parameters:
+            ModelParameters {
+                units,
+                failure_rate,
+                service_time,
+                service_startup_time,
+            },
         critical_value,
         time,
         start_state,
     } = parameters;
     assert_eq!(service_startup_time, 0.);
 
-    let markov_queue_probabilities = MarkovQueueProbabilities::new(failure_rate, 1./service_time, units);
+    let markov_queue_probabilities =
+        MarkovQueueProbabilities::new(failure_rate, 1. / service_time, units);
     let mut process = ContinuousMarkovProcess::new(markov_queue_probabilities, start_state);
-    while process.state() < critical_value {
-        if process.time() > time {
-            return 0.;
-        }
-        process.step(rng);
+    let stop = process.run_until(|state| state >= critical_value, time, rng);
+    if stop.reason() == StopReason::Hit {
+        1.
+    } else {
+        0.
     }
-    return 1.;
 }
 
-fn theory(_parameters: &Parameters) -> f64 {
-    0.9999956263
+fn theory(parameters: &Parameters) -> f64 {
+    let &Parameters {
+        model_parameters,
+        critical_value,
+        time,
+        start_state,
+    } = parameters;
+    let generator = model_parameters.generator(critical_value);
+    let mut initial = Vector::zeros(generator.nrows());
+    initial[start_state as usize] = 1.;
+    transient_distribution(&generator, &initial, time, 1e-12)[critical_value as usize]
 }
 
 pub fn main() {
@@ -56,13 +70,6 @@ pub fn main() {
         start_state: 0,
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        10_000_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 10_000_000, &mut rng);
+    println!("{result}");
 }
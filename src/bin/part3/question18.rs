@@ -1,62 +1,71 @@
 use rand::Rng;
-use rand_distr::{Distribution, Uniform, Exp, Poisson};
+use rand_distr::{Distribution, Exp, Uniform};
 use rand_pcg::Pcg64Mcg;
-use stoc::test_theory;
+use serde::Serialize;
+use stoc::{test_theory, GeneralQueueSystem, StochasticProcess};
 
-use crate::{ModelParameters, MAX_THREADS, SEED};
+use crate::{ModelParameters, SEED};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     min_run_time: f64,
     max_run_time: f64,
 }
 
-struct Process {
-    state: u64,
-    time: f64,
-    service_startup_time: f64,
+/// Service time of a unit that first takes a fixed time to start up.
+struct StartupService {
+    startup_time: f64,
+    exp_mean: f64,
     service_exp_distr: Exp<f64>,
-    failure_rate: f64,
 }
 
-impl Process {
+impl StartupService {
     fn new(model_parameters: ModelParameters) -> Self {
         let ModelParameters {
-            units,
-            failure_rate,
             service_time,
             service_startup_time,
+            ..
         } = model_parameters;
-        assert_eq!(units, 1, "This model only supports 1 unit");
+        let exp_mean = service_time - service_startup_time;
         Self {
-            state: 0,
-            time: 0.,
-            service_startup_time: service_startup_time,
-            service_exp_distr: Exp::new(1./(service_time-service_startup_time)).unwrap(),
-            failure_rate,
+            startup_time: service_startup_time,
+            exp_mean,
+            service_exp_distr: Exp::new(1. / exp_mean).unwrap(),
         }
     }
+}
 
-    fn state(&self) -> u64 {
-        self.state
+impl StartupService {
+    /// First three raw moments of the startup time plus an exponential.
+    fn moments(&self) -> [f64; 3] {
+        let d = self.startup_time;
+        let m = self.exp_mean;
+        [
+            d + m,
+            d * d + 2. * d * m + 2. * m * m,
+            d * d * d + 3. * d * d * m + 6. * d * m * m + 6. * m * m * m,
+        ]
     }
+}
 
-    fn time(&self) -> f64 {
-        self.time
-    }
+/// Stationary mean and variance of the number of customers in an M/G/1 queue, from the
+/// Pollaczek–Khinchine transform.
+fn queue_length_moments(arrival_rate: f64, service: &StartupService) -> (f64, f64) {
+    let [s1, s2, s3] = service.moments();
+    let rho = arrival_rate * s1;
+    assert!(rho < 1., "The queue must be stable, rho = {rho}");
+    // Factorial moments of the number of arrivals during one service.
+    let a2 = arrival_rate.powi(2) * s2;
+    let a3 = arrival_rate.powi(3) * s3;
+    let mean = rho + a2 / (2. * (1. - rho));
+    let second_factorial_moment = (a2 * a2 / 2. + (1. - rho) * (a2 + a3 / 3.)) / (1. - rho).powi(2);
+    (mean, second_factorial_moment + mean - mean * mean)
+}
 
-    fn step(&mut self, rng: &mut impl Rng) {
-        if self.state == 0 {
-            self.state = 1;
-            self.time += Exp::new(self.failure_rate).unwrap().sample(rng);
-        } else {
-            let delta_time = self.service_startup_time + self.service_exp_distr.sample(rng);
-            let new_failures = Poisson::new(self.failure_rate * delta_time).unwrap().sample(rng) as u64;
-            self.state -= 1;
-            self.state += new_failures;
-            self.time += delta_time;
-        }
+impl Distribution<f64> for StartupService {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        self.startup_time + self.service_exp_distr.sample(rng)
     }
 }
 
@@ -67,21 +76,26 @@ fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
         max_run_time,
     } = parameters;
 
-    let mut process = Process::new(model_parameters);
+    assert_eq!(model_parameters.units, 1, "This model only supports 1 unit");
+    let service = StartupService::new(model_parameters);
+    let (mean_x, _) = queue_length_moments(model_parameters.failure_rate, &service);
+    let mut queue = GeneralQueueSystem::new(
+        Some(1),
+        Exp::new(model_parameters.failure_rate).unwrap(),
+        service,
+        0,
+        rng,
+    );
     let run_time = Uniform::new(min_run_time, max_run_time).sample(rng);
-    let mut old_state = 0;
-    while process.time() < run_time {
-        old_state = process.state();
-        process.step(rng);
-    }
-    let x = old_state as f64;
-    let rho = process.failure_rate*model_parameters.service_time;
-    let mean_x = rho+rho*rho/(1.-rho);
-    (x-mean_x)*(x-mean_x)
+    queue.advance_to(run_time, rng);
+    let x = queue.state() as f64;
+    (x - mean_x) * (x - mean_x)
 }
 
-fn theory(_parameters: &Parameters) -> f64 {
-    f64::NAN
+fn theory(parameters: &Parameters) -> f64 {
+    let model_parameters = parameters.model_parameters;
+    let service = StartupService::new(model_parameters);
+    queue_length_moments(model_parameters.failure_rate, &service).1
 }
 
 pub fn main() {
@@ -91,20 +105,13 @@ pub fn main() {
         model_parameters: ModelParameters {
             units: 1,
             failure_rate: 4.,
-            service_time: 2./9.,
-            service_startup_time: 1./8.,
+            service_time: 2. / 9.,
+            service_startup_time: 1. / 8.,
         },
         min_run_time: 100.,
         max_run_time: 200.,
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        1_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 1_000, &mut rng);
+    println!("{result}");
 }
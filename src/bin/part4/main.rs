@@ -1,6 +1,8 @@
-use std::{env};
+use std::env;
+
 use rand::Rng;
 use rand_distr::{Distribution, Normal};
+use serde::Serialize;
 
 mod question20;
 mod question21;
@@ -10,9 +12,8 @@ mod question26;
 mod question27;
 
 const SEED: u128 = 0;
-const MAX_THREADS: u32 = 8;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct ModelParameters {
     pub det_mean: f64,
     pub det_var: f64,
@@ -44,15 +45,22 @@ pub struct Process {
 }
 
 impl Process {
-
     fn from_params(parameters: ModelParameters, start_state: f64, step_size: f64) -> Self {
         Self {
             step_size,
             state: start_state,
             cur_time: 0.,
-            det_distr: Normal::new(parameters.det_mean*step_size, (parameters.det_var*step_size).sqrt()).unwrap(),
-            rep_distr: Normal::new(parameters.rep_mean*step_size, (parameters.rep_var*step_size).sqrt()).unwrap(),
-            discount_factor: (-parameters.self_reversion*step_size).exp(),
+            det_distr: Normal::new(
+                parameters.det_mean * step_size,
+                (parameters.det_var * step_size).sqrt(),
+            )
+            .unwrap(),
+            rep_distr: Normal::new(
+                parameters.rep_mean * step_size,
+                (parameters.rep_var * step_size).sqrt(),
+            )
+            .unwrap(),
+            discount_factor: (-parameters.self_reversion * step_size).exp(),
         }
     }
 
@@ -74,19 +82,24 @@ impl Process {
     }
 }
 
-struct OuProcess{
+struct OuProcess {
     start_state: f64,
     diffusion: f64,
     drift: f64,
 }
 
 impl OuProcess {
-
     fn from_params(model_parameters: ModelParameters, start_state: f64) -> Self {
-        let ModelParameters { det_mean:_, det_var, rep_mean: _, rep_var, self_reversion } = model_parameters;
+        let ModelParameters {
+            det_mean: _,
+            det_var,
+            rep_mean: _,
+            rep_var,
+            self_reversion,
+        } = model_parameters;
         let drift = self_reversion;
-        let diffusion = (drift/(1.-(-drift).exp())*(det_var + rep_var)).sqrt();
-        
+        let diffusion = (drift / (1. - (-drift).exp()) * (det_var + rep_var)).sqrt();
+
         Self {
             start_state,
             diffusion,
@@ -95,9 +108,13 @@ impl OuProcess {
     }
 
     fn sample(&self, time: f64, rng: &mut impl Rng) -> f64 {
-        let &Self { start_state, diffusion, drift } = self;
-        let normal = Normal::new(0., ((2.*drift*time).exp()-1.).sqrt()).unwrap();
-        (-drift*time).exp()*(start_state + diffusion/(2.*drift).sqrt()*normal.sample(rng))
+        let &Self {
+            start_state,
+            diffusion,
+            drift,
+        } = self;
+        let normal = Normal::new(0., ((2. * drift * time).exp() - 1.).sqrt()).unwrap();
+        (-drift * time).exp() * (start_state + diffusion / (2. * drift).sqrt() * normal.sample(rng))
     }
 }
 
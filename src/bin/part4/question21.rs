@@ -1,10 +1,11 @@
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
+use serde::Serialize;
 use stoc::test_theory;
 
-use crate::{ModelParameters, Process, MAX_THREADS, SEED};
+use crate::{ModelParameters, Process, SEED};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     start_state: f64,
@@ -41,21 +42,25 @@ fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
 
 fn theory(parameters: &Parameters) -> f64 {
     let &Parameters {
-        model_parameters: ModelParameters{
+        model_parameters:
+            ModelParameters {
                 det_mean,
                 det_var,
                 rep_mean,
                 rep_var,
-            self_reversion
-        },
+                self_reversion,
+            },
         start_state: x,
         critical_value: _,
     } = parameters;
-    assert_eq!(self_reversion, 0., "Self-reversion is not supported in this question.");
-    let mu = det_mean-rep_mean;
-    let sigma_squared = det_var+rep_var;
+    assert_eq!(
+        self_reversion, 0.,
+        "Self-reversion is not supported in this question."
+    );
+    let mu = det_mean - rep_mean;
+    let sigma_squared = det_var + rep_var;
 
-    (-2.*mu*x/sigma_squared).exp()
+    (-2. * mu * x / sigma_squared).exp()
 }
 
 pub fn main() {
@@ -63,13 +68,6 @@ pub fn main() {
 
     let parameters = Parameters::default();
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        10_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 10_000, &mut rng);
+    println!("{result}");
 }
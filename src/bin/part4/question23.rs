@@ -1,11 +1,12 @@
 use rand::Rng;
-use rand_distr::{Exp, Distribution};
+use rand_distr::{Distribution, Exp};
 use rand_pcg::Pcg64Mcg;
+use serde::Serialize;
 use stoc::test_theory;
 
-use crate::{ModelParameters, Process, MAX_THREADS, SEED};
+use crate::{ModelParameters, Process, SEED};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     start_state: f64,
@@ -37,7 +38,7 @@ fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
 
     let mut process = Process::from_params(model_parameters, start_state, 0.001);
 
-    let inspection_time = Exp::new(1./6.).unwrap().sample(rng);
+    let inspection_time = Exp::new(1. / 6.).unwrap().sample(rng);
 
     while process.time() <= inspection_time {
         process.step(rng);
@@ -59,13 +60,6 @@ pub fn main() {
 
     let parameters = Parameters::default();
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        100_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 100_000, &mut rng);
+    println!("{result}");
 }
@@ -1,10 +1,11 @@
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
+use serde::Serialize;
 use stoc::test_theory;
 
-use crate::{ModelParameters, Process, MAX_THREADS, SEED};
+use crate::{ModelParameters, Process, SEED};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     start_state: f64,
@@ -59,7 +60,9 @@ fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
         process.step(rng);
     }
     let return_state = process.state();
-    if value1-time1*return_state/return_time > b && value2 - time2*return_state/return_time > b {
+    if value1 - time1 * return_state / return_time > b
+        && value2 - time2 * return_state / return_time > b
+    {
         1.
     } else {
         0.
@@ -75,13 +78,6 @@ pub fn main() {
 
     let parameters = Parameters::default();
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        100_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 100_000, &mut rng);
+    println!("{result}");
 }
@@ -1,11 +1,12 @@
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
+use serde::Serialize;
 use statrs::distribution::ContinuousCDF;
 use stoc::test_theory;
 
-use crate::{ModelParameters, Process, MAX_THREADS, SEED, OuProcess};
+use crate::{ModelParameters, OuProcess, Process, SEED};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     start_state: f64,
@@ -18,7 +19,7 @@ struct Parameters {
 impl Default for Parameters {
     fn default() -> Self {
         let beta = 0.1f64;
-        let det_var = beta/(1.-(-beta).exp())*3.;
+        let det_var = beta / (1. - (-beta).exp()) * 3.;
         Self {
             model_parameters: ModelParameters {
                 det_mean: 0.,
@@ -48,7 +49,7 @@ fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
 
     if use_ou_process {
         let process = OuProcess::from_params(model_parameters, start_state);
-        if process.sample(time, rng) > b{
+        if process.sample(time, rng) > b {
             1.
         } else {
             0.
@@ -68,25 +69,26 @@ fn experiment(parameters: &Parameters, rng: &mut impl Rng) -> f64 {
 
 fn theory(parameters: &Parameters) -> f64 {
     let &Parameters {
-        model_parameters: ModelParameters{
+        model_parameters:
+            ModelParameters {
                 det_mean: _,
                 det_var,
                 rep_mean: _,
                 rep_var,
-            self_reversion: beta
-        },
+                self_reversion: beta,
+            },
         start_state: nu,
         time: t,
         critical_value: b,
         step_size: _,
         use_ou_process: _,
     } = parameters;
-    let sigma_squared = det_var+rep_var;
-    let mean = nu*(-beta*t).exp();
-    let variance = sigma_squared*(1.-(-2.*beta*t).exp())/(2.*beta);
+    let sigma_squared = det_var + rep_var;
+    let mean = nu * (-beta * t).exp();
+    let variance = sigma_squared * (1. - (-2. * beta * t).exp()) / (2. * beta);
 
     let distr = statrs::distribution::Normal::new(mean, variance.sqrt()).unwrap();
-    1.-distr.cdf(b)
+    1. - distr.cdf(b)
 }
 
 pub fn main() {
@@ -98,13 +100,6 @@ pub fn main() {
         ..Parameters::default()
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        40_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 40_000, &mut rng);
+    println!("{result}");
 }
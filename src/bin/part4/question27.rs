@@ -1,10 +1,11 @@
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
+use serde::Serialize;
 use stoc::test_theory;
 
-use crate::{ModelParameters, Process, MAX_THREADS, SEED};
+use crate::{ModelParameters, Process, SEED};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 struct Parameters {
     model_parameters: ModelParameters,
     min_run_time: f64,
@@ -18,7 +19,7 @@ struct Parameters {
 impl Default for Parameters {
     fn default() -> Self {
         let beta = 0.1f64;
-        let det_var = beta/(1.-(-beta).exp())*3.;
+        let det_var = beta / (1. - (-beta).exp()) * 3.;
         Self {
             model_parameters: ModelParameters {
                 det_mean: 0.,
@@ -81,13 +82,6 @@ pub fn main() {
         ..Parameters::default()
     };
 
-    let result = test_theory(
-        experiment,
-        theory,
-        &parameters,
-        4_000,
-        MAX_THREADS,
-        &mut rng,
-    );
-    println!("{result:?}");
+    let result = test_theory(experiment, theory, &parameters, 4_000, &mut rng);
+    println!("{result}");
 }
@@ -0,0 +1,241 @@
+use crate::{BirthAndDeathProbabilities, Vector};
+
+/// Largest number of states summed when testing an infinite birth-death chain for positive
+/// recurrence.
+const MAX_STATES: usize = 1 << 20;
+
+/// Stationary behaviour of a birth-death chain from the product form
+/// `pi_k = pi_0 * prod_{i < k} lambda_i / mu_{i + 1}`.
+#[derive(Debug, Clone)]
+pub struct BirthDeathAnalysis {
+    distribution: Vector,
+    birth_rates: Vector,
+    death_rates: Vector,
+}
+
+impl BirthDeathAnalysis {
+    /// Unnormalised stationary weights and the rates of the states `0..num_states`, stopping early
+    /// once `done` returns true for the weights so far and the ratio to the next weight.
+    fn weights(
+        model: &impl BirthAndDeathProbabilities,
+        num_states: usize,
+        mut done: impl FnMut(&[f64], f64) -> bool,
+    ) -> Option<Self> {
+        let mut weights = vec![1.];
+        let mut birth_rates = Vec::new();
+        let mut death_rates = vec![model.probability_tuple(0).1];
+        loop {
+            let k = weights.len() - 1;
+            let birth_rate = model.probability_tuple(k as u64).0;
+            birth_rates.push(birth_rate);
+            if k + 1 == num_states {
+                break;
+            }
+            let death_rate = model.probability_tuple(k as u64 + 1).1;
+            assert!(
+                death_rate > 0. || birth_rate == 0.,
+                "State {} can be entered but has no death rate",
+                k + 1
+            );
+            let ratio = if birth_rate == 0. {
+                0.
+            } else {
+                birth_rate / death_rate
+            };
+            if done(&weights, ratio) {
+                break;
+            }
+            weights.push(weights[k] * ratio);
+            death_rates.push(death_rate);
+            if !weights[k + 1].is_finite() {
+                return None;
+            }
+        }
+        let total: f64 = weights.iter().sum();
+        Some(Self {
+            distribution: Vector::from_vec(weights) / total,
+            birth_rates: Vector::from_vec(birth_rates),
+            death_rates: Vector::from_vec(death_rates),
+        })
+    }
+
+    /// The chain on the states `0..num_states`, ignoring births out of the last state.
+    pub fn finite(model: &impl BirthAndDeathProbabilities, num_states: usize) -> Self {
+        assert!(num_states > 0, "A chain must have at least one state");
+        Self::weights(model, num_states, |_, _| false).unwrap()
+    }
+
+    /// The chain on all states, truncated once the remaining stationary mass is below `tolerance`.
+    ///
+    /// Returns `None` if the chain is not positive recurrent, i.e. the series of stationary weights
+    /// does not converge within [`MAX_STATES`] states. The tail is bounded geometrically by the
+    /// last ratio `lambda_k / mu_{k + 1}`, assuming the ratios do not grow after the truncation
+    /// point.
+    pub fn infinite(model: &impl BirthAndDeathProbabilities, tolerance: f64) -> Option<Self> {
+        assert!(
+            tolerance > 0. && tolerance < 1.,
+            "Tolerance must be in (0, 1). Got {tolerance}"
+        );
+        let mut total = 0.;
+        let mut converged = false;
+        let analysis = Self::weights(model, MAX_STATES, |weights, ratio| {
+            let weight = weights[weights.len() - 1];
+            total += weight;
+            converged = ratio < 1. && weight * ratio / (1. - ratio) <= tolerance * total;
+            converged
+        })?;
+        converged.then_some(analysis)
+    }
+
+    /// Stationary probabilities of the states up to the truncation point.
+    pub fn distribution(&self) -> &Vector {
+        &self.distribution
+    }
+
+    /// Stationary probability of `state`, zero beyond the truncation point.
+    pub fn probability(&self, state: u64) -> f64 {
+        self.distribution.get(state as usize).copied().unwrap_or(0.)
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.distribution
+            .iter()
+            .enumerate()
+            .map(|(k, probability)| k as f64 * probability)
+            .sum()
+    }
+
+    pub fn variance(&self) -> f64 {
+        let mean = self.mean();
+        self.distribution
+            .iter()
+            .enumerate()
+            .map(|(k, probability)| (k as f64 - mean).powi(2) * probability)
+            .sum()
+    }
+
+    /// Expected time for the chain to first reach level `to` from level `from`.
+    ///
+    /// Moving up one level from `k` takes `P(X <= k) / (pi_k lambda_k)` on average and moving down
+    /// one level `P(X >= k) / (pi_k mu_k)`.
+    pub fn expected_first_passage_time(&self, from: u64, to: u64) -> f64 {
+        let num_states = self.distribution.len() as u64;
+        assert!(
+            from < num_states && to < num_states,
+            "Levels must be below the truncation point {num_states}"
+        );
+        if from <= to {
+            let mut below = self.distribution.iter().take(from as usize).sum::<f64>();
+            (from..to)
+                .map(|k| {
+                    let probability = self.distribution[k as usize];
+                    below += probability;
+                    below / (probability * self.birth_rates[k as usize])
+                })
+                .sum()
+        } else {
+            let mut above = self
+                .distribution
+                .iter()
+                .skip(from as usize + 1)
+                .sum::<f64>();
+            (to + 1..=from)
+                .rev()
+                .map(|k| {
+                    let probability = self.distribution[k as usize];
+                    above += probability;
+                    above / (probability * self.death_rates[k as usize])
+                })
+                .sum()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::assert_close, MarkovQueueProbabilities};
+
+    #[test]
+    fn mm1_is_geometric() {
+        let rho = 0.6;
+        let analysis =
+            BirthDeathAnalysis::infinite(&MarkovQueueProbabilities::new(rho, 1., 1), 1e-13)
+                .unwrap();
+        for k in 0..20 {
+            assert_close(analysis.probability(k), (1. - rho) * rho.powi(k as i32));
+        }
+        assert_close(analysis.mean(), rho / (1. - rho));
+        assert_close(analysis.variance(), rho / (1. - rho).powi(2));
+    }
+
+    #[test]
+    fn mmc_mean_matches_erlang_c() {
+        let (num_units, arrival_rate, service_rate) = (3, 2.4, 1.);
+        let analysis = BirthDeathAnalysis::infinite(
+            &MarkovQueueProbabilities::new(arrival_rate, service_rate, num_units),
+            1e-13,
+        )
+        .unwrap();
+
+        let (a, c) = (arrival_rate / service_rate, num_units as f64);
+        let mut term = 1.;
+        let mut idle_sum = 0.;
+        for k in 0..num_units {
+            idle_sum += term;
+            term *= a / (k + 1) as f64;
+        }
+        let busy_term = term * c / (c - a);
+        let wait_probability = busy_term / (idle_sum + busy_term);
+        assert_close(analysis.mean(), wait_probability * a / (c - a) + a);
+    }
+
+    #[test]
+    fn infinite_rejects_unstable_chains() {
+        for arrival_rate in [1., 1.5] {
+            let model = MarkovQueueProbabilities::new(arrival_rate, 1., 1);
+            assert!(BirthDeathAnalysis::infinite(&model, 1e-9).is_none());
+        }
+    }
+
+    #[test]
+    fn first_passage_times_match_recursions() {
+        let num_states = 12;
+        let model = MarkovQueueProbabilities::with_capacity(1.5, 2., 2, num_states as u64 - 1);
+        let analysis = BirthDeathAnalysis::finite(&model, num_states);
+        let rates = |k: usize| model.probability_tuple(k as u64);
+
+        // Up from k: 1 / λ_k + (μ_k / λ_k) times the time up from k - 1.
+        let mut up = vec![0.; num_states - 1];
+        for k in 0..num_states - 1 {
+            let (birth_rate, death_rate) = rates(k);
+            let below = if k > 0 { up[k - 1] } else { 0. };
+            up[k] = (1. + death_rate * below) / birth_rate;
+            assert_close(
+                analysis.expected_first_passage_time(k as u64, k as u64 + 1),
+                up[k],
+            );
+        }
+        // Down from k: 1 / μ_k + (λ_k / μ_k) times the time down from k + 1.
+        let mut down = vec![0.; num_states];
+        for k in (1..num_states).rev() {
+            let (birth_rate, death_rate) = rates(k);
+            let above = if k + 1 < num_states { down[k + 1] } else { 0. };
+            down[k] = (1. + birth_rate * above) / death_rate;
+            assert_close(
+                analysis.expected_first_passage_time(k as u64, k as u64 - 1),
+                down[k],
+            );
+        }
+
+        assert_close(
+            analysis.expected_first_passage_time(2, 7),
+            up[2..7].iter().sum(),
+        );
+        assert_close(
+            analysis.expected_first_passage_time(9, 1),
+            down[2..=9].iter().sum(),
+        );
+        assert_eq!(analysis.expected_first_passage_time(4, 4), 0.);
+    }
+}
@@ -1,27 +1,76 @@
 use rand::Rng;
 use rand_distr::{Distribution, Normal};
 
+use crate::StochasticProcess;
+
+/// Time between the events of a motion as a [`StochasticProcess`] unless set otherwise.
+const DEFAULT_TIME_STEP: f64 = 0.01;
+
 pub struct BrownianMotion {
     cur_value: f64,
+    cur_t: f64,
     step_distr: Normal<f64>,
+    time_step: f64,
 }
 
 impl BrownianMotion {
     pub fn initialize() -> Self {
         Self {
             cur_value: 0.,
+            cur_t: 0.,
             step_distr: Normal::new(0., 1.).unwrap(),
+            time_step: DEFAULT_TIME_STEP,
         }
     }
 
+    /// Sets the time between the events of the motion as a [`StochasticProcess`], 0.01 by default.
+    pub fn with_time_step(self, time_step: f64) -> Self {
+        assert!(
+            time_step > 0.,
+            "Time step must be positive. Got {time_step}"
+        );
+        Self { time_step, ..self }
+    }
+
     pub fn step(&mut self, step_size: f64, rng: &mut impl Rng) -> f64 {
         self.cur_value += self.step_distr.sample(rng) * step_size.sqrt();
+        self.cur_t += step_size;
         self.cur_value
     }
 
     pub fn cur_value(&self) -> f64 {
         self.cur_value
     }
+
+    pub fn cur_t(&self) -> f64 {
+        self.cur_t
+    }
+}
+
+impl StochasticProcess for BrownianMotion {
+    type State = f64;
+
+    fn time(&self) -> f64 {
+        self.cur_t
+    }
+
+    fn state(&self) -> f64 {
+        self.cur_value
+    }
+
+    fn next_event(&mut self, rng: &mut impl Rng) -> bool {
+        self.step(self.time_step, rng);
+        true
+    }
+
+    fn advance_to(&mut self, time: f64, rng: &mut impl Rng) {
+        assert!(
+            time >= self.cur_t,
+            "Cannot step backwards in time. Current time: {}, requested time: {time}",
+            self.cur_t
+        );
+        self.step(time - self.cur_t, rng);
+    }
 }
 
 pub struct GeometricBrownianMotion {
@@ -53,6 +102,14 @@ impl GeometricBrownianMotion {
         self.cur_t
     }
 
+    /// Sets the time between the events of the motion as a [`StochasticProcess`], 0.01 by default.
+    pub fn with_time_step(self, time_step: f64) -> Self {
+        Self {
+            base_motion: self.base_motion.with_time_step(time_step),
+            ..self
+        }
+    }
+
     pub fn step(&mut self, step_size: f64, rng: &mut impl Rng) -> f64 {
         self.cur_t += step_size;
         self.cur_value = self.start_value
@@ -61,3 +118,53 @@ impl GeometricBrownianMotion {
         self.cur_value
     }
 }
+
+impl StochasticProcess for GeometricBrownianMotion {
+    type State = f64;
+
+    fn time(&self) -> f64 {
+        self.cur_t
+    }
+
+    fn state(&self) -> f64 {
+        self.cur_value
+    }
+
+    fn next_event(&mut self, rng: &mut impl Rng) -> bool {
+        self.step(self.base_motion.time_step, rng);
+        true
+    }
+
+    fn advance_to(&mut self, time: f64, rng: &mut impl Rng) {
+        assert!(
+            time >= self.cur_t,
+            "Cannot step backwards in time. Current time: {}, requested time: {time}",
+            self.cur_t
+        );
+        self.step(time - self.cur_t, rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_pcg::Pcg64Mcg;
+
+    use super::*;
+
+    #[test]
+    fn events_without_a_time_step_use_the_default() {
+        let mut rng = Pcg64Mcg::new(0);
+        let mut motion = BrownianMotion::initialize();
+        let times: Vec<_> = motion
+            .events(&mut rng)
+            .take(3)
+            .map(|(time, _)| time)
+            .collect();
+        assert_eq!(times, [0.01, 0.02, 0.03]);
+
+        let mut motion = GeometricBrownianMotion::initialize(1., 0.1, 0.04).with_time_step(0.5);
+        assert!(motion.next_event(&mut rng));
+        assert_eq!(motion.time(), 0.5);
+        assert!(motion.state() > 0.);
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt::{self, Display};
+
+/// Theory and simulation for a single component of a sample.
+#[derive(Debug, Clone)]
+pub struct ComparisonRow {
+    pub name: String,
+    pub theory: f64,
+    pub empirical: f64,
+    pub standard_error: f64,
+    pub z_score: f64,
+    pub p_value: f64,
+    /// Whether the component rejects the theory at the corrected significance level.
+    pub disagrees: bool,
+}
+
+/// Component-by-component comparison of an empirical mean with its theoretical value.
+///
+/// Components are flagged as disagreeing using a Bonferroni correction, i.e. when their p-value is
+/// below the significance level divided by the number of components.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    rows: Vec<ComparisonRow>,
+    significance: f64,
+    total_variation_distance: f64,
+    chi_square_distance: f64,
+}
+
+impl Comparison {
+    pub(crate) fn new(
+        names: Vec<String>,
+        theory: Vec<f64>,
+        empirical: Vec<f64>,
+        standard_errors: Vec<f64>,
+        p_values: Vec<f64>,
+        significance: f64,
+    ) -> Self {
+        assert!(
+            significance > 0. && significance < 1.,
+            "Significance level must be in (0, 1). Got {significance}"
+        );
+        let corrected_significance = significance / names.len() as f64;
+        let rows: Vec<_> = names
+            .into_iter()
+            .zip(theory)
+            .zip(empirical)
+            .zip(standard_errors)
+            .zip(p_values)
+            .map(
+                |((((name, theory), empirical), standard_error), p_value)| ComparisonRow {
+                    name,
+                    theory,
+                    empirical,
+                    standard_error,
+                    z_score: (empirical - theory) / standard_error,
+                    p_value,
+                    disagrees: p_value < corrected_significance,
+                },
+            )
+            .collect();
+        let total_variation_distance = rows
+            .iter()
+            .map(|row| (row.empirical - row.theory).abs())
+            .sum::<f64>()
+            / 2.;
+        let chi_square_distance = rows
+            .iter()
+            .filter(|row| row.theory > 0.)
+            .map(|row| (row.empirical - row.theory).powi(2) / row.theory)
+            .sum();
+        Self {
+            rows,
+            significance,
+            total_variation_distance,
+            chi_square_distance,
+        }
+    }
+
+    pub fn rows(&self) -> &[ComparisonRow] {
+        &self.rows
+    }
+
+    pub fn disagreeing(&self) -> impl Iterator<Item = &ComparisonRow> {
+        self.rows.iter().filter(|row| row.disagrees)
+    }
+
+    pub fn all_agree(&self) -> bool {
+        self.disagreeing().next().is_none()
+    }
+
+    /// Whether the theoretical components are non-negative and sum to at most one, i.e. form a
+    /// (possibly truncated) distribution, so the distances are meaningful.
+    pub fn is_distribution(&self) -> bool {
+        self.rows.iter().all(|row| row.theory >= 0.)
+            && self.rows.iter().map(|row| row.theory).sum::<f64>() <= 1. + 1e-9
+    }
+
+    /// Half the sum of absolute differences. Only meaningful when the components form a distribution.
+    pub fn total_variation_distance(&self) -> f64 {
+        self.total_variation_distance
+    }
+
+    /// Pearson chi-square divergence of the empirical mean from the theory, skipping components
+    /// with a theoretical value of zero. Only meaningful when the components form a distribution.
+    pub fn chi_square_distance(&self) -> f64 {
+        self.chi_square_distance
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .rows
+            .iter()
+            .map(|row| row.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("component".len());
+        writeln!(
+            f,
+            "{:<name_width$}  {:>12}  {:>12}  {:>12}  {:>9}  {:>9}",
+            "component", "theory", "empirical", "std. error", "z-score", "p-value"
+        )?;
+        for row in &self.rows {
+            writeln!(
+                f,
+                "{:<name_width$}  {:>12.6}  {:>12.6}  {:>12.6}  {:>9.3}  {:>9.4}{}",
+                row.name,
+                row.theory,
+                row.empirical,
+                row.standard_error,
+                row.z_score,
+                row.p_value,
+                if row.disagrees { "  *" } else { "" }
+            )?;
+        }
+        write!(
+            f,
+            "* disagrees at significance {} (Bonferroni corrected)",
+            self.significance
+        )?;
+        if self.is_distribution() {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "total variation distance: {}",
+                self.total_variation_distance
+            )?;
+            write!(f, "chi-square distance:      {}", self.chi_square_distance)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comparison(theory: Vec<f64>, empirical: Vec<f64>, p_values: Vec<f64>) -> Comparison {
+        let names = (0..theory.len()).map(|k| k.to_string()).collect();
+        let standard_errors = vec![0.1; theory.len()];
+        Comparison::new(names, theory, empirical, standard_errors, p_values, 0.05)
+    }
+
+    #[test]
+    fn distances_between_distributions() {
+        let comparison = comparison(
+            vec![0.5, 0.25, 0.25, 0.],
+            vec![0.4, 0.3, 0.2, 0.1],
+            vec![0.5; 4],
+        );
+        assert!(comparison.is_distribution());
+        // (0.1 + 0.05 + 0.05 + 0.1) / 2
+        assert!((comparison.total_variation_distance() - 0.15).abs() < 1e-12);
+        // 0.1² / 0.5 + 0.05² / 0.25 + 0.05² / 0.25, the last component having no theoretical mass.
+        assert!((comparison.chi_square_distance() - 0.04).abs() < 1e-12);
+        assert!(comparison.to_string().contains("chi-square distance"));
+    }
+
+    #[test]
+    fn distances_vanish_on_agreement() {
+        let comparison = comparison(vec![0.3, 0., 0.7], vec![0.3, 0., 0.7], vec![1.; 3]);
+        assert_eq!(comparison.total_variation_distance(), 0.);
+        assert_eq!(comparison.chi_square_distance(), 0.);
+    }
+
+    #[test]
+    fn distances_are_not_shown_for_other_samples() {
+        for theory in [vec![0.8, 0.7], vec![1.5, -0.5]] {
+            let comparison = comparison(theory, vec![0.5, 0.5], vec![0.5; 2]);
+            assert!(!comparison.is_distribution());
+            assert!(!comparison.to_string().contains("distance"));
+        }
+    }
+
+    #[test]
+    fn disagreement_uses_bonferroni_correction() {
+        // 0.05 / 4 = 0.0125
+        let comparison = comparison(
+            vec![1., 2., 3., 4.],
+            vec![1.1, 2.4, 3., 3.9],
+            vec![0.02, 0.001, 1., 0.2],
+        );
+        let disagreeing: Vec<_> = comparison
+            .disagreeing()
+            .map(|row| row.name.as_str())
+            .collect();
+        assert_eq!(disagreeing, ["1"]);
+        assert!(!comparison.all_agree());
+        assert!((comparison.rows()[1].z_score - 4.).abs() < 1e-12);
+    }
+}
@@ -1,51 +1,172 @@
 use ndarray::Axis;
 use rand::Rng;
-use rand_distr::{Exp, Distribution};
+use rand_distr::{Distribution, Exp};
 
-use crate::{Matrix, Vector};
+use crate::{validate_generator, Matrix, StochasticProcess, Trajectory, TransitionMatrix, Vector};
 
 pub trait BirthAndDeathProbabilities {
-    fn probability_tuple(&self, from_state: u64) -> (f64, f64) ;
+    fn probability_tuple(&self, from_state: u64) -> (f64, f64);
+
+    /// Generator of the chain on the states below `num_states`, dropping the rates that would
+    /// leave them.
+    fn truncated_generator(&self, num_states: usize) -> Matrix {
+        let mut generator = Matrix::zeros((num_states, num_states));
+        for k in 0..num_states {
+            let (birth_rate, death_rate) = self.probability_tuple(k as u64);
+            if k + 1 < num_states {
+                generator[[k, k + 1]] = birth_rate;
+                generator[[k, k]] -= birth_rate;
+            }
+            if k > 0 {
+                generator[[k, k - 1]] = death_rate;
+                generator[[k, k]] -= death_rate;
+            }
+        }
+        generator
+    }
+
+    /// Transitions of the chain truncated to the states below `num_states`, for analysis with the
+    /// matrix tools.
+    fn truncated(&self, num_states: usize) -> MatrixTransitions {
+        MatrixTransitions::new(self.truncated_generator(num_states))
+    }
 }
 
+/// Queue with Poisson arrivals and `num_units` exponential servers, holding at most `capacity`
+/// customers if it has one (M/M/c/K) and unboundedly many otherwise (M/M/c).
 #[derive(Debug, Clone)]
 pub struct MarkovQueueProbabilities {
     arrival_rate: f64,
     service_rate: f64,
     num_units: u64,
+    capacity: Option<u64>,
 }
 
 impl MarkovQueueProbabilities {
     pub fn new(arrival_rate: f64, service_rate: f64, num_units: u64) -> Self {
         assert!(arrival_rate > 0.);
         assert!(service_rate > 0.);
+        assert!(num_units > 0, "A queue must have at least one server");
         Self {
             arrival_rate,
             service_rate,
             num_units,
+            capacity: None,
         }
     }
+
+    /// A queue that turns away arrivals when `capacity` customers are in the system.
+    pub fn with_capacity(
+        arrival_rate: f64,
+        service_rate: f64,
+        num_units: u64,
+        capacity: u64,
+    ) -> Self {
+        assert!(
+            capacity >= num_units,
+            "Capacity {capacity} must be at least the number of servers {num_units}"
+        );
+        Self {
+            capacity: Some(capacity),
+            ..Self::new(arrival_rate, service_rate, num_units)
+        }
+    }
+
+    pub fn capacity(&self) -> Option<u64> {
+        self.capacity
+    }
 }
 
 impl BirthAndDeathProbabilities for MarkovQueueProbabilities {
     fn probability_tuple(&self, from_state: u64) -> (f64, f64) {
-        let birth_rate = self.arrival_rate;
+        let birth_rate = match self.capacity {
+            Some(capacity) if from_state >= capacity => 0.,
+            _ => self.arrival_rate,
+        };
+        let death_rate = self.service_rate * from_state.min(self.num_units) as f64;
+        (birth_rate, death_rate)
+    }
+}
+
+/// A population of `population` individuals, each of which enters the system at
+/// `arrival_rate` while outside it, served by `num_units` servers at `service_rate` each.
+///
+/// With as many servers as individuals this is the machine repair model in which every broken
+/// machine is repaired independently.
+#[derive(Debug, Clone)]
+pub struct FinitePopulationProbabilities {
+    population: u64,
+    arrival_rate: f64,
+    service_rate: f64,
+    num_units: u64,
+}
+
+impl FinitePopulationProbabilities {
+    pub fn new(population: u64, arrival_rate: f64, service_rate: f64, num_units: u64) -> Self {
+        assert!(arrival_rate > 0.);
+        assert!(service_rate > 0.);
+        assert!(num_units > 0, "A queue must have at least one server");
+        Self {
+            population,
+            arrival_rate,
+            service_rate,
+            num_units,
+        }
+    }
+
+    pub fn population(&self) -> u64 {
+        self.population
+    }
+}
+
+impl BirthAndDeathProbabilities for FinitePopulationProbabilities {
+    fn probability_tuple(&self, from_state: u64) -> (f64, f64) {
+        let birth_rate = self.arrival_rate * self.population.saturating_sub(from_state) as f64;
         let death_rate = self.service_rate * from_state.min(self.num_units) as f64;
         (birth_rate, death_rate)
     }
 }
 
 pub trait ContinuousMarkovTransitions {
-    fn next_transition(&self, from_state: u64, rng: &mut impl Rng) -> Option<(u64, f64)>;
+    type State: Clone;
+
+    /// The next state and the time until the jump to it when in `from_state` at `time`, or `None`
+    /// if `from_state` is absorbing.
+    fn next_transition(
+        &mut self,
+        from_state: &Self::State,
+        time: f64,
+        rng: &mut impl Rng,
+    ) -> Option<(Self::State, f64)>;
 }
 
 impl<T> ContinuousMarkovTransitions for T
 where
     T: BirthAndDeathProbabilities,
 {
-    fn next_transition(&self, from_state: u64, rng: &mut impl Rng) -> Option<(u64, f64)> {
+    type State = u64;
+
+    fn next_transition(
+        &mut self,
+        from_state: &u64,
+        _time: f64,
+        rng: &mut impl Rng,
+    ) -> Option<(u64, f64)> {
+        let from_state = *from_state;
         let (birth_rate, death_rate) = self.probability_tuple(from_state);
+        assert!(
+            birth_rate >= 0. && death_rate >= 0.,
+            "Rates must be non-negative. Got ({birth_rate}, {death_rate}) in state {from_state}"
+        );
+        assert!(
+            from_state > 0 || death_rate == 0.,
+            "State 0 must have no death rate. Got {death_rate}"
+        );
         let total_rate = birth_rate + death_rate;
+        if total_rate == 0. {
+            // The process stays in an absorbing state forever.
+            return None;
+        }
         let time_to_next_transition = Exp::new(total_rate).unwrap().sample(rng);
         let next_state = if rng.gen_bool(birth_rate / total_rate) {
             from_state + 1
@@ -56,8 +177,179 @@ where
     }
 }
 
+/// Next jump of a birth-death chain in `from_state` at `time` whose (birth, death) rates at each
+/// time are given by `rates`, by Lewis-Shedler thinning: candidate jumps arrive at `rate_bound`
+/// and each is kept with probability the total rate at its time over the bound.
+///
+/// `rate_bound` must bound the total rate at all later times, and the rates must not vanish
+/// forever unless they are zero for all later times.
+fn thin(
+    from_state: u64,
+    time: f64,
+    rate_bound: f64,
+    rates: impl Fn(f64) -> (f64, f64),
+    rng: &mut impl Rng,
+) -> Option<(u64, f64)> {
+    if rate_bound == 0. {
+        return None;
+    }
+    let candidates = Exp::new(rate_bound).unwrap();
+    let mut candidate_time = time;
+    loop {
+        candidate_time += candidates.sample(rng);
+        let (birth_rate, death_rate) = rates(candidate_time);
+        assert!(
+            birth_rate >= 0. && death_rate >= 0.,
+            "Rates must be non-negative. Got ({birth_rate}, {death_rate}) in state {from_state} \
+             at time {candidate_time}"
+        );
+        assert!(
+            birth_rate + death_rate <= rate_bound,
+            "Total rate {} in state {from_state} at time {candidate_time} exceeds the bound \
+             {rate_bound}",
+            birth_rate + death_rate
+        );
+        assert!(
+            from_state > 0 || death_rate == 0.,
+            "State 0 must have no death rate. Got {death_rate}"
+        );
+        // One uniform both thins the candidate and picks the jump.
+        let rng_value = rng.gen_range(0. ..rate_bound);
+        if rng_value < birth_rate {
+            return Some((from_state + 1, candidate_time - time));
+        }
+        if rng_value < birth_rate + death_rate {
+            return Some((from_state - 1, candidate_time - time));
+        }
+    }
+}
+
+/// Birth-death chain whose (birth, death) rates in each state depend on the time, simulated
+/// exactly by thinning given a bound on the total rate out of any state at any time.
+#[derive(Debug, Clone)]
+pub struct TimeDependentBirthAndDeath<F>
+where
+    F: Fn(u64, f64) -> (f64, f64),
+{
+    rates: F,
+    rate_bound: f64,
+}
+
+impl<F> TimeDependentBirthAndDeath<F>
+where
+    F: Fn(u64, f64) -> (f64, f64),
+{
+    /// `rates` gives the (birth, death) rates in a state at a time.
+    pub fn new(rates: F, rate_bound: f64) -> Self {
+        assert!(
+            rate_bound >= 0. && rate_bound.is_finite(),
+            "Rate bound must be non-negative. Got {rate_bound}"
+        );
+        Self { rates, rate_bound }
+    }
+}
+
+impl<F> ContinuousMarkovTransitions for TimeDependentBirthAndDeath<F>
+where
+    F: Fn(u64, f64) -> (f64, f64),
+{
+    type State = u64;
+
+    fn next_transition(
+        &mut self,
+        from_state: &u64,
+        time: f64,
+        rng: &mut impl Rng,
+    ) -> Option<(u64, f64)> {
+        let from_state = *from_state;
+        let rates = |time| (self.rates)(from_state, time);
+        thin(from_state, time, self.rate_bound, rates, rng)
+    }
+}
+
+/// [`MarkovQueueProbabilities`] with a time-dependent arrival rate `arrival_rate(t)`, e.g. following
+/// a daily cycle, bounded by `max_arrival_rate`.
+#[derive(Debug, Clone)]
+pub struct InhomogeneousMarkovQueueProbabilities<F>
+where
+    F: Fn(f64) -> f64,
+{
+    arrival_rate: F,
+    max_arrival_rate: f64,
+    service_rate: f64,
+    num_units: u64,
+    capacity: Option<u64>,
+}
+
+impl<F> InhomogeneousMarkovQueueProbabilities<F>
+where
+    F: Fn(f64) -> f64,
+{
+    pub fn new(arrival_rate: F, max_arrival_rate: f64, service_rate: f64, num_units: u64) -> Self {
+        assert!(max_arrival_rate > 0. && max_arrival_rate.is_finite());
+        assert!(service_rate > 0.);
+        assert!(num_units > 0, "A queue must have at least one server");
+        Self {
+            arrival_rate,
+            max_arrival_rate,
+            service_rate,
+            num_units,
+            capacity: None,
+        }
+    }
+
+    /// A queue that turns away arrivals when `capacity` customers are in the system.
+    pub fn with_capacity(
+        arrival_rate: F,
+        max_arrival_rate: f64,
+        service_rate: f64,
+        num_units: u64,
+        capacity: u64,
+    ) -> Self {
+        assert!(
+            capacity >= num_units,
+            "Capacity {capacity} must be at least the number of servers {num_units}"
+        );
+        Self {
+            capacity: Some(capacity),
+            ..Self::new(arrival_rate, max_arrival_rate, service_rate, num_units)
+        }
+    }
+
+    /// The (birth, death) rates in `from_state` at `time`.
+    pub fn probability_tuple(&self, from_state: u64, time: f64) -> (f64, f64) {
+        let birth_rate = match self.capacity {
+            Some(capacity) if from_state >= capacity => 0.,
+            _ => (self.arrival_rate)(time),
+        };
+        let death_rate = self.service_rate * from_state.min(self.num_units) as f64;
+        (birth_rate, death_rate)
+    }
+}
+
+impl<F> ContinuousMarkovTransitions for InhomogeneousMarkovQueueProbabilities<F>
+where
+    F: Fn(f64) -> f64,
+{
+    type State = u64;
+
+    fn next_transition(
+        &mut self,
+        from_state: &u64,
+        time: f64,
+        rng: &mut impl Rng,
+    ) -> Option<(u64, f64)> {
+        let from_state = *from_state;
+        // Departures are at a constant rate while in the state, so only arrivals are thinned.
+        let rate_bound = self.max_arrival_rate + self.probability_tuple(from_state, time).1;
+        let rates = |time| self.probability_tuple(from_state, time);
+        thin(from_state, time, rate_bound, rates, rng)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MatrixTransitions {
+    generator: Matrix,
     total_rates: Vector,
     cumulative_rows: Matrix,
 }
@@ -66,9 +358,9 @@ impl MatrixTransitions {
     pub fn new(transitions: Matrix) -> Self {
         assert!(transitions.is_square(), "Transition matrix must be square");
         let total_rates = -transitions.diag().to_owned();
-        let mut cumulative_rows = transitions;
+        // Rows are binary searched, so they must be contiguous whatever the caller's layout.
+        let mut cumulative_rows = transitions.as_standard_layout().into_owned();
         for (k, mut row) in cumulative_rows.axis_iter_mut(Axis(0)).enumerate() {
-
             let total_rate = total_rates[k];
             if total_rate == 0. {
                 continue;
@@ -81,43 +373,202 @@ impl MatrixTransitions {
                 *rate = cumulative_rate;
             }
         }
-        Self { total_rates, cumulative_rows }
+        Self {
+            generator: transitions,
+            total_rates,
+            cumulative_rows,
+        }
+    }
+
+    /// The matrix the transitions were created from.
+    pub fn generator(&self) -> &Matrix {
+        &self.generator
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.total_rates.len()
+    }
+
+    /// The discrete-time chain of the states visited, ignoring how long each is held. States
+    /// without transitions become absorbing.
+    ///
+    /// The generator's rows must sum to zero.
+    pub fn embedded_jump_chain(&self) -> TransitionMatrix {
+        validate_generator(&self.generator);
+        let mut transitions = self.generator.clone();
+        for (k, mut row) in transitions.axis_iter_mut(Axis(0)).enumerate() {
+            let total_rate = self.total_rates[k];
+            if total_rate == 0. {
+                row.fill(0.);
+                row[k] = 1.;
+            } else {
+                row[k] = 0.;
+                row /= total_rate;
+            }
+        }
+        TransitionMatrix::new(transitions)
+    }
+
+    /// The chain observed at the jumps of a Poisson process of the given `rate`, which must be at
+    /// least the largest total rate of a state. It has the same stationary distributions as the
+    /// continuous-time chain.
+    ///
+    /// The generator's rows must sum to zero.
+    pub fn uniformized_chain(&self, rate: f64) -> TransitionMatrix {
+        validate_generator(&self.generator);
+        let max_rate = self
+            .total_rates
+            .iter()
+            .fold(0., |max: f64, &rate| max.max(rate));
+        assert!(
+            rate >= max_rate && rate > 0.,
+            "Uniformization rate must be positive and at least the largest total rate {max_rate}. Got {rate}"
+        );
+        TransitionMatrix::new(Matrix::eye(self.num_states()) + &self.generator / rate)
+    }
+
+    /// Turns a stationary distribution of the embedded jump chain into one of this chain by
+    /// weighting each state with its mean holding time.
+    pub fn stationary_from_embedded(&self, embedded: &Vector) -> Vector {
+        self.reweight(embedded, |total_rate| 1. / total_rate)
+    }
+
+    /// Turns a stationary distribution of this chain into one of the embedded jump chain by
+    /// weighting each state with its total rate.
+    pub fn stationary_to_embedded(&self, stationary: &Vector) -> Vector {
+        self.reweight(stationary, |total_rate| total_rate)
+    }
+
+    fn reweight(&self, distribution: &Vector, weight: impl Fn(f64) -> f64) -> Vector {
+        assert_eq!(
+            distribution.len(),
+            self.num_states(),
+            "Distribution must have one entry per state"
+        );
+        let mut reweighted = Vector::zeros(self.num_states());
+        for (k, (&probability, &total_rate)) in
+            distribution.iter().zip(&self.total_rates).enumerate()
+        {
+            if probability == 0. {
+                continue;
+            }
+            assert!(
+                total_rate > 0.,
+                "Cannot reweight mass in state {k}, which has no transitions"
+            );
+            reweighted[k] = probability * weight(total_rate);
+        }
+        let total = reweighted.sum();
+        reweighted / total
     }
 }
 
 impl ContinuousMarkovTransitions for MatrixTransitions {
-    fn next_transition(&self, from_state: u64, rng: &mut impl Rng) -> Option<(u64, f64)> {
-        let from_state = from_state as usize;
-        assert!(from_state <= self.total_rates.dim(), "Invalid state. Maximum state is {}", self.total_rates.dim());
+    type State = u64;
+
+    fn next_transition(
+        &mut self,
+        from_state: &u64,
+        _time: f64,
+        rng: &mut impl Rng,
+    ) -> Option<(u64, f64)> {
+        let from_state = *from_state as usize;
+        assert!(
+            from_state <= self.total_rates.dim(),
+            "Invalid state. Maximum state is {}",
+            self.total_rates.dim()
+        );
         if from_state == self.total_rates.dim() {
             None
         } else {
             let cumulative_rates = self.cumulative_rows.row(from_state);
             let total_rate = self.total_rates[from_state];
+            if total_rate == 0. {
+                // The process stays in an absorbing state forever.
+                return None;
+            }
             let time_to_next_transition = Exp::new(total_rate).unwrap().sample(rng);
 
             let rng_value = rng.gen_range(0. ..1.);
-            let next_state = cumulative_rates.iter().position(|&cumulative_rate| {cumulative_rate > rng_value}).unwrap_or(cumulative_rates.len()) as u64;
+            // Past the last cumulative rate is the implicit absorbing state.
+            let next_state = cumulative_rates
+                .as_slice()
+                .unwrap()
+                .partition_point(|&cumulative_rate| cumulative_rate <= rng_value)
+                as u64;
             Some((next_state, time_to_next_transition))
         }
     }
 }
 
+/// Why [`ContinuousMarkovProcess::run_until`] stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The predicate held.
+    Hit,
+    /// The deadline came first.
+    Deadline,
+    /// The process was absorbed without the predicate holding, and there was no deadline.
+    Absorbed,
+}
+
+/// Where and why [`ContinuousMarkovProcess::run_until`] stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stop<S> {
+    reason: StopReason,
+    time: f64,
+    state: S,
+}
+
+impl<S> Stop<S> {
+    pub fn reason(&self) -> StopReason {
+        self.reason
+    }
+
+    /// The hitting time for [`StopReason::Hit`], otherwise the deadline or the time of absorption.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct ContinuousMarkovProcess<M> where M: ContinuousMarkovTransitions {
-    cur_state: u64,
+pub struct ContinuousMarkovProcess<M>
+where
+    M: ContinuousMarkovTransitions,
+{
+    cur_state: M::State,
     absorbed: bool,
     cur_time: f64,
     transitions: M,
+    trajectory: Option<Trajectory<M::State>>,
+    /// The next jump as (time, state) once it has been drawn, kept when a run stops before it.
+    pending: Option<(f64, M::State)>,
 }
 
-impl<M> ContinuousMarkovProcess<M> where M: ContinuousMarkovTransitions {
-    pub fn new(transitions: M, start_state: u64) -> Self {
+impl<M> ContinuousMarkovProcess<M>
+where
+    M: ContinuousMarkovTransitions,
+{
+    pub fn new(transitions: M, start_state: M::State) -> Self {
         Self {
             cur_state: start_state,
             cur_time: 0.,
             transitions,
             absorbed: false,
+            trajectory: None,
+            pending: None,
+        }
+    }
+
+    /// Like [`ContinuousMarkovProcess::new`], but records every jump in a [`Trajectory`].
+    pub fn with_trajectory(transitions: M, start_state: M::State) -> Self {
+        Self {
+            trajectory: Some(Trajectory::new(0., start_state.clone())),
+            ..Self::new(transitions, start_state)
         }
     }
 
@@ -125,23 +576,394 @@ impl<M> ContinuousMarkovProcess<M> where M: ContinuousMarkovTransitions {
         self.cur_time
     }
 
-    pub fn state(&self) -> u64 {
-        self.cur_state
+    pub fn state(&self) -> M::State {
+        self.cur_state.clone()
     }
 
     pub fn is_absorbed(&self) -> bool {
         self.absorbed
     }
 
-    pub fn step(&mut self, rng: &mut impl Rng) {
-        if self.absorbed {
-            return;
+    /// The path so far, if the process was created with
+    /// [`ContinuousMarkovProcess::with_trajectory`].
+    pub fn trajectory(&self) -> Option<&Trajectory<M::State>> {
+        self.trajectory.as_ref()
+    }
+
+    /// Time of the next jump, drawing it unless it is already pending. `None` once absorbed.
+    fn next_jump_time(&mut self, rng: &mut impl Rng) -> Option<f64> {
+        if self.pending.is_none() && !self.absorbed {
+            match self
+                .transitions
+                .next_transition(&self.cur_state, self.cur_time, rng)
+            {
+                Some((next_state, time_delta)) => {
+                    self.pending = Some((self.cur_time + time_delta, next_state));
+                }
+                None => self.absorbed = true,
+            }
         }
-        if let Some((next_state, time_delta)) = self.transitions.next_transition(self.cur_state, rng) {
+        self.pending.as_ref().map(|&(time, _)| time)
+    }
+
+    pub fn step(&mut self, rng: &mut impl Rng) {
+        self.next_jump_time(rng);
+        if let Some((time, next_state)) = self.pending.take() {
+            self.cur_time = time;
+            if let Some(trajectory) = &mut self.trajectory {
+                trajectory.push(time, next_state.clone());
+            }
             self.cur_state = next_state;
-            self.cur_time += time_delta;
-        } else {
-            self.absorbed = true;
         }
     }
+
+    /// Runs the process until `predicate` holds for its state or until `deadline`, whichever comes
+    /// first, without overshooting the deadline: a jump after it is kept for the next step.
+    ///
+    /// With an infinite deadline the run only ends once the predicate holds or the process is
+    /// absorbed.
+    pub fn run_until(
+        &mut self,
+        predicate: impl Fn(M::State) -> bool,
+        deadline: f64,
+        rng: &mut impl Rng,
+    ) -> Stop<M::State> {
+        assert!(
+            deadline >= self.cur_time,
+            "Deadline {deadline} is before the current time {}",
+            self.cur_time
+        );
+        let reason = loop {
+            if predicate(self.cur_state.clone()) {
+                break StopReason::Hit;
+            }
+            match self.next_jump_time(rng) {
+                Some(time) if time <= deadline => self.step(rng),
+                None if deadline == f64::INFINITY => break StopReason::Absorbed,
+                _ => {
+                    self.cur_time = deadline;
+                    break StopReason::Deadline;
+                }
+            }
+        };
+        Stop {
+            reason,
+            time: self.cur_time,
+            state: self.cur_state.clone(),
+        }
+    }
+
+    /// Runs the process up to exactly `time` and returns its state then.
+    pub fn run_until_time(&mut self, time: f64, rng: &mut impl Rng) -> M::State {
+        assert!(time.is_finite(), "Time must be finite. Got {time}");
+        self.run_until(|_| false, time, rng).state
+    }
+
+    /// Runs the process until its state is in the set accepted by `in_set` and returns the
+    /// hitting time, or `None` if the process is absorbed outside the set.
+    pub fn run_until_hit(
+        &mut self,
+        in_set: impl Fn(M::State) -> bool,
+        rng: &mut impl Rng,
+    ) -> Option<f64> {
+        let stop = self.run_until(in_set, f64::INFINITY, rng);
+        (stop.reason == StopReason::Hit).then_some(stop.time)
+    }
+}
+
+impl<M> StochasticProcess for ContinuousMarkovProcess<M>
+where
+    M: ContinuousMarkovTransitions,
+{
+    type State = M::State;
+
+    fn time(&self) -> f64 {
+        self.cur_time
+    }
+
+    fn state(&self) -> M::State {
+        self.cur_state.clone()
+    }
+
+    fn next_event(&mut self, rng: &mut impl Rng) -> bool {
+        if self.next_jump_time(rng).is_none() {
+            return false;
+        }
+        self.step(rng);
+        true
+    }
+
+    fn advance_to(&mut self, time: f64, rng: &mut impl Rng) {
+        self.run_until_time(time, rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+    use rand_pcg::Pcg64Mcg;
+
+    use super::*;
+    use crate::{test_util::assert_close_vectors, BirthDeathAnalysis};
+
+    #[test]
+    fn matrix_transitions_accept_column_major_generators() {
+        let generator = array![[-1., 2.], [1., -2.]].reversed_axes();
+        assert!(!generator.is_standard_layout());
+        let mut rng = Pcg64Mcg::new(0);
+        let mut process = ContinuousMarkovProcess::new(MatrixTransitions::new(generator), 0);
+        let occupation = process.occupation(0., 20_000., &mut rng);
+        assert!((occupation[&0] - 2. / 3.).abs() < 0.02);
+    }
+
+    /// Long-run fraction of time in each of the first states, from one long path.
+    fn occupation_of<M>(transitions: M, num_states: u64, seed: u128) -> Vec<f64>
+    where
+        M: ContinuousMarkovTransitions<State = u64>,
+    {
+        let mut rng = Pcg64Mcg::new(seed);
+        let mut process = ContinuousMarkovProcess::new(transitions, 0);
+        let occupation = process.occupation(0., 50_000., &mut rng);
+        (0..num_states)
+            .map(|state| occupation.get(&state).copied().unwrap_or(0.))
+            .collect()
+    }
+
+    #[test]
+    fn constant_time_dependent_rates_give_homogeneous_law() {
+        // M/M/1 with ρ = 1/2 is geometric.
+        let expected: Vec<f64> = (0..4).map(|k| 0.5f64.powi(k + 1)).collect();
+        let thinned =
+            TimeDependentBirthAndDeath::new(|state, _| (1., if state > 0 { 2. } else { 0. }), 5.);
+        let inhomogeneous = InhomogeneousMarkovQueueProbabilities::new(|_| 1., 1.5, 2., 1);
+        for occupation in [
+            occupation_of(MarkovQueueProbabilities::new(1., 2., 1), 4, 1),
+            occupation_of(thinned, 4, 2),
+            occupation_of(inhomogeneous, 4, 3),
+        ] {
+            for (probability, expected) in occupation.iter().zip(&expected) {
+                assert!(
+                    (probability - expected).abs() < 0.01,
+                    "{occupation:?} vs {expected:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn finite_population_matches_birth_death_analysis() {
+        let (population, arrival_rate, service_rate) = (6, 0.5, 1.);
+        let num_states = population as usize + 1;
+
+        // With a server per individual, each one is in the system independently with probability
+        // λ / (λ + μ).
+        let model = FinitePopulationProbabilities::new(population, arrival_rate, service_rate, 6);
+        let analysis = BirthDeathAnalysis::finite(&model, num_states);
+        let busy = arrival_rate / (arrival_rate + service_rate);
+        let mut binomial = 1.;
+        for k in 0..num_states {
+            let expected =
+                binomial * busy.powi(k as i32) * (1. - busy).powi((population - k as u64) as i32);
+            assert!(
+                (analysis.probability(k as u64) - expected).abs() < 1e-12,
+                "State {k}: {} vs {expected}",
+                analysis.probability(k as u64)
+            );
+            binomial *= (population - k as u64) as f64 / (k + 1) as f64;
+        }
+
+        // With fewer servers individuals queue, and the simulated occupation follows the product
+        // form.
+        let model = FinitePopulationProbabilities::new(population, arrival_rate, service_rate, 2);
+        let analysis = BirthDeathAnalysis::finite(&model, num_states);
+        let occupation = occupation_of(model, population + 1, 5);
+        for (probability, expected) in occupation.iter().zip(analysis.distribution()) {
+            assert!(
+                (probability - expected).abs() < 0.01,
+                "{occupation:?} vs {}",
+                analysis.distribution()
+            );
+        }
+    }
+
+    /// Linear birth-death chain, absorbed once the population dies out.
+    struct Extinction;
+
+    impl BirthAndDeathProbabilities for Extinction {
+        fn probability_tuple(&self, from_state: u64) -> (f64, f64) {
+            (0.5 * from_state as f64, from_state as f64)
+        }
+    }
+
+    #[test]
+    fn birth_death_process_stops_at_an_absorbing_boundary() {
+        let mut rng = Pcg64Mcg::new(0);
+        assert!(Extinction.next_transition(&0, 0., &mut rng).is_none());
+
+        let mut process = ContinuousMarkovProcess::new(Extinction, 3);
+        let stop = process.run_until(|_| false, f64::INFINITY, &mut rng);
+        assert_eq!(stop.reason(), StopReason::Absorbed);
+        assert_eq!(*stop.state(), 0);
+        assert!(process.is_absorbed());
+        assert!(!process.next_event(&mut rng));
+        assert_eq!(process.time(), stop.time());
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the bound")]
+    fn rates_above_the_bound_panic() {
+        let mut rng = Pcg64Mcg::new(0);
+        let transitions = TimeDependentBirthAndDeath::new(|_, time| (1. + time, 0.), 2.);
+        let mut process = ContinuousMarkovProcess::new(transitions, 0);
+        process.run_until_time(10., &mut rng);
+    }
+
+    #[test]
+    fn infinite_server_mean_follows_arrival_rate() {
+        // M_t/M/∞ from empty: the mean is ∫₀^t λ(s) e^{-μ(t - s)} ds, here with
+        // λ(s) = a + b sin(ωs).
+        let (a, b, omega, service_rate): (f64, f64, f64, f64) = (1., 0.8, 2., 1.5);
+        let time: f64 = 3.;
+        let decay = (-service_rate * time).exp();
+        let expected = a * (1. - decay) / service_rate
+            + b * (service_rate * (omega * time).sin() - omega * (omega * time).cos()
+                + omega * decay)
+                / (service_rate * service_rate + omega * omega);
+
+        let mut rng = Pcg64Mcg::new(4);
+        let runs = 20_000;
+        let total: u64 = (0..runs)
+            .map(|_| {
+                let transitions = InhomogeneousMarkovQueueProbabilities::new(
+                    |time: f64| a + b * (omega * time).sin(),
+                    a + b,
+                    service_rate,
+                    u64::MAX,
+                );
+                ContinuousMarkovProcess::new(transitions, 0).run_until_time(time, &mut rng)
+            })
+            .sum();
+        let mean = total as f64 / runs as f64;
+        // The state is Poisson, so the standard error is about (expected / runs)^(1/2) ≈ 0.008.
+        assert!((mean - expected).abs() < 0.04, "{mean} vs {expected}");
+    }
+
+    /// Moves up one state after each of the given holding times, then is absorbed.
+    struct Scripted(Vec<f64>);
+
+    impl ContinuousMarkovTransitions for Scripted {
+        type State = u64;
+
+        fn next_transition(
+            &mut self,
+            from_state: &u64,
+            _time: f64,
+            _rng: &mut impl Rng,
+        ) -> Option<(u64, f64)> {
+            let holding_time = self.0.get(*from_state as usize)?;
+            Some((from_state + 1, *holding_time))
+        }
+    }
+
+    /// Jumps at times 1, 3 and 3.5, then absorbed in state 3.
+    fn scripted() -> ContinuousMarkovProcess<Scripted> {
+        ContinuousMarkovProcess::with_trajectory(Scripted(vec![1., 2., 0.5]), 0)
+    }
+
+    #[test]
+    fn run_until_stops_when_the_predicate_holds() {
+        let mut rng = Pcg64Mcg::new(0);
+        let stop = scripted().run_until(|state| state == 2, 10., &mut rng);
+        assert_eq!(stop.reason(), StopReason::Hit);
+        assert_eq!((stop.time(), *stop.state()), (3., 2));
+    }
+
+    #[test]
+    fn run_until_stops_at_the_deadline() {
+        let mut rng = Pcg64Mcg::new(0);
+        let mut process = scripted();
+        let stop = process.run_until(|state| state == 3, 2., &mut rng);
+        assert_eq!(stop.reason(), StopReason::Deadline);
+        assert_eq!((stop.time(), *stop.state()), (2., 1));
+        assert_eq!(process.time(), 2.);
+
+        // Absorbed before a finite deadline, the run still ends at the deadline.
+        let stop = process.run_until(|_| false, 5., &mut rng);
+        assert_eq!(stop.reason(), StopReason::Deadline);
+        assert_eq!((stop.time(), *stop.state()), (5., 3));
+    }
+
+    #[test]
+    fn run_until_takes_a_jump_exactly_at_the_deadline() {
+        let mut rng = Pcg64Mcg::new(0);
+        let stop = scripted().run_until(|_| false, 3., &mut rng);
+        assert_eq!(stop.reason(), StopReason::Deadline);
+        assert_eq!((stop.time(), *stop.state()), (3., 2));
+    }
+
+    #[test]
+    fn run_until_keeps_the_pending_jump() {
+        let mut rng = Pcg64Mcg::new(0);
+        let mut process = scripted();
+        assert_eq!(process.run_until_time(2., &mut rng), 1);
+        // Redrawing the jump at time 2 would move it to 4.
+        assert_eq!(process.run_until_time(3.2, &mut rng), 2);
+        let jump_times: Vec<_> = process
+            .trajectory()
+            .unwrap()
+            .jumps()
+            .iter()
+            .map(|&(time, _)| time)
+            .collect();
+        assert_eq!(jump_times, [0., 1., 3.]);
+    }
+
+    #[test]
+    fn run_until_reports_absorption() {
+        let mut rng = Pcg64Mcg::new(0);
+        let stop = scripted().run_until(|_| false, f64::INFINITY, &mut rng);
+        assert_eq!(stop.reason(), StopReason::Absorbed);
+        assert_eq!((stop.time(), *stop.state()), (3.5, 3));
+
+        // State 1 has no transitions.
+        let transitions = MatrixTransitions::new(array![[-1., 1.], [0., 0.]]);
+        let mut process = ContinuousMarkovProcess::new(transitions, 0);
+        let stop = process.run_until(|state| state == 5, f64::INFINITY, &mut rng);
+        assert_eq!(stop.reason(), StopReason::Absorbed);
+        assert_eq!(*stop.state(), 1);
+        assert!(process.is_absorbed());
+        assert_eq!(process.run_until_hit(|state| state == 0, &mut rng), None);
+    }
+
+    fn three_state_generator() -> Matrix {
+        array![[-3., 2., 1.], [1., -1., 0.], [2., 2., -4.]]
+    }
+
+    #[test]
+    fn stationary_round_trips_through_the_embedded_chain() {
+        let transitions = MatrixTransitions::new(three_state_generator());
+        let stationary = crate::stationary_distribution(transitions.generator());
+        let embedded = transitions.embedded_jump_chain().stationary_distribution();
+        assert_close_vectors(&transitions.stationary_to_embedded(&stationary), &embedded);
+        assert_close_vectors(
+            &transitions.stationary_from_embedded(&embedded),
+            &stationary,
+        );
+    }
+
+    #[test]
+    fn uniformized_chain_keeps_the_stationary_distribution() {
+        let transitions = MatrixTransitions::new(three_state_generator());
+        let stationary = crate::stationary_distribution(transitions.generator());
+        for rate in [4., 10.] {
+            let uniformized = transitions.uniformized_chain(rate);
+            assert_close_vectors(&uniformized.stationary_distribution(), &stationary);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least the largest total rate 4")]
+    fn uniformization_rate_below_the_exit_rates_panics() {
+        MatrixTransitions::new(three_state_generator()).uniformized_chain(3.9);
+    }
 }
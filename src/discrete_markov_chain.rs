@@ -0,0 +1,370 @@
+use std::collections::VecDeque;
+
+use ndarray::Axis;
+use rand::{Rng, RngCore};
+
+use crate::{
+    communicating_classes, stationary::validate_substochastic, stationary_distribution,
+    stationary_distributions, CommunicatingClass, Matrix, StationaryDistribution,
+    StochasticProcess, Vector,
+};
+
+pub trait DiscreteMarkovTransitions {
+    fn next_state(&self, from_state: u64, rng: &mut impl Rng) -> u64;
+}
+
+/// Transitions given by a closure sampling the next state from the current one, for chains
+/// whose kernel is easier to sample than to write down.
+#[derive(Debug, Clone)]
+pub struct ClosureTransitions<F>
+where
+    F: Fn(u64, &mut dyn RngCore) -> u64,
+{
+    sample_next_state: F,
+}
+
+impl<F> ClosureTransitions<F>
+where
+    F: Fn(u64, &mut dyn RngCore) -> u64,
+{
+    pub fn new(sample_next_state: F) -> Self {
+        Self { sample_next_state }
+    }
+}
+
+impl<F> DiscreteMarkovTransitions for ClosureTransitions<F>
+where
+    F: Fn(u64, &mut dyn RngCore) -> u64,
+{
+    fn next_state(&self, from_state: u64, rng: &mut impl Rng) -> u64 {
+        (self.sample_next_state)(from_state, rng)
+    }
+}
+
+/// Transitions given by a stochastic matrix, which also gives access to the analytic properties
+/// of the chain.
+#[derive(Debug, Clone)]
+pub struct TransitionMatrix {
+    matrix: Matrix,
+    cumulative_rows: Matrix,
+}
+
+impl TransitionMatrix {
+    pub fn new(matrix: Matrix) -> Self {
+        validate_substochastic(&matrix);
+        for (i, row) in matrix.outer_iter().enumerate() {
+            let sum = row.sum();
+            assert!(
+                sum >= 1. - 1e-9,
+                "Rows of a transition matrix must sum to one. Row {i} sums to {sum}"
+            );
+        }
+        // Rows are binary searched, so they must be contiguous whatever the caller's layout.
+        let mut cumulative_rows = matrix.as_standard_layout().into_owned();
+        cumulative_rows.accumulate_axis_inplace(Axis(1), |&previous, next| *next += previous);
+        Self {
+            matrix,
+            cumulative_rows,
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix {
+        &self.matrix
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.matrix.nrows()
+    }
+
+    /// The `steps`-step transition matrix, computed by repeated squaring.
+    pub fn n_step_matrix(&self, steps: u64) -> Matrix {
+        let mut result = Matrix::eye(self.num_states());
+        let mut power = self.matrix.clone();
+        let mut steps = steps;
+        while steps > 0 {
+            if steps % 2 == 1 {
+                result = result.dot(&power);
+            }
+            steps /= 2;
+            if steps > 0 {
+                power = power.dot(&power);
+            }
+        }
+        result
+    }
+
+    /// Distribution after `steps` steps of the chain started from `initial`.
+    pub fn n_step_distribution(&self, initial: &Vector, steps: u64) -> Vector {
+        assert_eq!(
+            initial.len(),
+            self.num_states(),
+            "Distribution must have one entry per state"
+        );
+        initial.dot(&self.n_step_matrix(steps))
+    }
+
+    pub fn communicating_classes(&self) -> Vec<CommunicatingClass> {
+        communicating_classes(&self.matrix)
+    }
+
+    pub fn is_irreducible(&self) -> bool {
+        self.communicating_classes().len() == 1
+    }
+
+    /// Greatest common divisor of the lengths of the cycles through `state`.
+    ///
+    /// The period is shared by the whole communicating class. It is 0 for a state that cannot
+    /// return to itself.
+    pub fn period(&self, state: usize) -> u64 {
+        let class = self
+            .communicating_classes()
+            .into_iter()
+            .find(|class| class.states().contains(&state))
+            .unwrap_or_else(|| panic!("Invalid state {state}"));
+        let states = class.states();
+
+        // Breadth-first levels within the class; every edge inside the class closes a cycle whose
+        // length is a multiple of the period when combined with the levels.
+        let mut levels = vec![None; self.num_states()];
+        levels[state] = Some(0i64);
+        let mut queue = VecDeque::from([state]);
+        let mut period = 0;
+        while let Some(i) = queue.pop_front() {
+            let level = levels[i].unwrap();
+            for &j in states {
+                if self.matrix[[i, j]] <= 0. {
+                    continue;
+                }
+                match levels[j] {
+                    None => {
+                        levels[j] = Some(level + 1);
+                        queue.push_back(j);
+                    }
+                    Some(other_level) => {
+                        period = num_integer::gcd(period, (level + 1 - other_level).unsigned_abs());
+                    }
+                }
+            }
+        }
+        period
+    }
+
+    /// Whether the chain is irreducible with period 1.
+    pub fn is_aperiodic(&self) -> bool {
+        self.is_irreducible() && self.period(0) == 1
+    }
+
+    /// States the chain never leaves.
+    pub fn absorbing_states(&self) -> Vec<usize> {
+        (0..self.num_states())
+            .filter(|&i| self.matrix[[i, i]] >= 1. - 1e-12)
+            .collect()
+    }
+
+    /// The stationary distribution of every closed class of the chain.
+    pub fn stationary_distributions(&self) -> Vec<StationaryDistribution> {
+        stationary_distributions(&self.generator())
+    }
+
+    /// The unique stationary distribution of a chain with a single closed class.
+    pub fn stationary_distribution(&self) -> Vector {
+        stationary_distribution(&self.generator())
+    }
+
+    /// `P - I`, whose stationary vectors are those of the chain.
+    fn generator(&self) -> Matrix {
+        let mut generator = &self.matrix - &Matrix::eye(self.num_states());
+        // Rows sum to one only up to rounding, make the generator exact.
+        for (i, mut row) in generator.axis_iter_mut(Axis(0)).enumerate() {
+            let sum = row.sum();
+            row[i] -= sum;
+        }
+        generator
+    }
+}
+
+impl DiscreteMarkovTransitions for TransitionMatrix {
+    fn next_state(&self, from_state: u64, rng: &mut impl Rng) -> u64 {
+        let from_state = from_state as usize;
+        assert!(
+            from_state < self.num_states(),
+            "Invalid state. Maximum state is {}",
+            self.num_states() - 1
+        );
+        let cumulative_probabilities = self.cumulative_rows.row(from_state);
+        let rng_value = rng.gen_range(0. ..1.);
+        let next_state = cumulative_probabilities
+            .as_slice()
+            .unwrap()
+            .partition_point(|&cumulative_probability| cumulative_probability <= rng_value);
+        if next_state < self.num_states() {
+            return next_state as u64;
+        }
+        // Rounding can leave the last cumulative probability just below one.
+        self.matrix
+            .row(from_state)
+            .iter()
+            .rposition(|&probability| probability > 0.)
+            .unwrap() as u64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscreteMarkovChain<M>
+where
+    M: DiscreteMarkovTransitions,
+{
+    cur_state: u64,
+    cur_time: u64,
+    transitions: M,
+}
+
+impl<M> DiscreteMarkovChain<M>
+where
+    M: DiscreteMarkovTransitions,
+{
+    pub fn new(transitions: M, start_state: u64) -> Self {
+        Self {
+            cur_state: start_state,
+            cur_time: 0,
+            transitions,
+        }
+    }
+
+    /// Number of steps taken.
+    pub fn time(&self) -> u64 {
+        self.cur_time
+    }
+
+    pub fn state(&self) -> u64 {
+        self.cur_state
+    }
+
+    pub fn transitions(&self) -> &M {
+        &self.transitions
+    }
+
+    pub fn step(&mut self, rng: &mut impl Rng) {
+        self.cur_state = self.transitions.next_state(self.cur_state, rng);
+        self.cur_time += 1;
+    }
+}
+
+/// The chain in continuous time, taking one step at each whole time.
+impl<M> StochasticProcess for DiscreteMarkovChain<M>
+where
+    M: DiscreteMarkovTransitions,
+{
+    type State = u64;
+
+    fn time(&self) -> f64 {
+        self.cur_time as f64
+    }
+
+    fn state(&self) -> u64 {
+        self.cur_state
+    }
+
+    fn next_event(&mut self, rng: &mut impl Rng) -> bool {
+        self.step(rng);
+        true
+    }
+
+    /// Takes every step up to `time`. The chain is left at the last whole time, where its state is
+    /// the state at `time`.
+    fn advance_to(&mut self, time: f64, rng: &mut impl Rng) {
+        assert!(
+            time >= self.cur_time as f64,
+            "Cannot step backwards in time. Current time: {}, requested time: {time}",
+            self.cur_time
+        );
+        while (self.cur_time + 1) as f64 <= time {
+            self.step(rng);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+    use rand::rngs::mock::StepRng;
+
+    use super::*;
+
+    #[test]
+    fn next_state_skips_states_without_probability() {
+        // The row sums to just below one, so the largest uniform lands past the last cumulative
+        // probability.
+        let transitions =
+            TransitionMatrix::new(array![[0.5, 0.5 - 1e-12, 0.], [0., 0., 1.], [1., 0., 0.],]);
+        let mut rng = StepRng::new(u64::MAX, 0);
+        assert_eq!(transitions.next_state(0, &mut rng), 1);
+        assert_eq!(transitions.next_state(1, &mut rng), 2);
+    }
+
+    #[test]
+    fn n_step_distribution_of_two_state_chain() {
+        // P^n = Π + (1 - a - b)^n (I - Π) with Π the matrix of stationary rows.
+        let (a, b) = (0.3, 0.6);
+        let transitions = TransitionMatrix::new(array![[1. - a, a], [b, 1. - b]]);
+        let initial = array![0.2, 0.8];
+        let stationary = array![b, a] / (a + b);
+        for steps in [0, 1, 5, 40] {
+            let distribution = transitions.n_step_distribution(&initial, steps);
+            let expected = &stationary + (&initial - &stationary) * (1. - a - b).powi(steps as i32);
+            assert!(
+                (&distribution - &expected).mapv(f64::abs).sum() < 1e-12,
+                "After {steps} steps"
+            );
+        }
+    }
+
+    #[test]
+    fn period_of_cycles() {
+        let cycle = TransitionMatrix::new(array![[0., 1., 0.], [0., 0., 1.], [1., 0., 0.]]);
+        assert_eq!(cycle.period(1), 3);
+        assert!(!cycle.is_aperiodic());
+
+        let lazy_cycle = TransitionMatrix::new(array![[0.5, 0.5, 0.], [0., 0., 1.], [1., 0., 0.]]);
+        assert_eq!(lazy_cycle.period(2), 1);
+        assert!(lazy_cycle.is_aperiodic());
+
+        // Cycles of lengths 2 and 4 through state 0.
+        let bipartite = TransitionMatrix::new(array![
+            [0., 0.5, 0., 0.5],
+            [0.5, 0., 0.5, 0.],
+            [0., 0.5, 0., 0.5],
+            [1., 0., 0., 0.],
+        ]);
+        assert_eq!(bipartite.period(0), 2);
+        assert_eq!(bipartite.period(3), 2);
+    }
+
+    #[test]
+    fn period_of_state_without_return() {
+        let transitions = TransitionMatrix::new(array![[0., 1.], [0., 1.]]);
+        assert_eq!(transitions.period(0), 0);
+        assert_eq!(transitions.period(1), 1);
+    }
+
+    #[test]
+    fn chain_as_a_stochastic_process() {
+        let cycle = TransitionMatrix::new(array![[0., 1., 0.], [0., 0., 1.], [1., 0., 0.]]);
+        let mut rng = StepRng::new(0, 1);
+        let mut chain = DiscreteMarkovChain::new(cycle.clone(), 0);
+        let events: Vec<_> = chain.events(&mut rng).take(4).collect();
+        assert_eq!(events, [(1., 1), (2., 2), (3., 0), (4., 1)]);
+
+        let mut chain = DiscreteMarkovChain::new(cycle.clone(), 0);
+        chain.advance_to(2.5, &mut rng);
+        assert_eq!(StochasticProcess::time(&chain), 2.);
+        assert_eq!(StochasticProcess::state(&chain), 2);
+        chain.advance_to(3., &mut rng);
+        assert_eq!(StochasticProcess::time(&chain), 3.);
+
+        // A third of the time in each state of the cycle.
+        let mut chain = DiscreteMarkovChain::new(cycle, 0);
+        let fraction = chain.time_average(|state| (state == 1) as u8 as f64, 0.5, 6.5, &mut rng);
+        assert!((fraction - 1. / 3.).abs() < 1e-12);
+    }
+}
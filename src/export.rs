@@ -0,0 +1,418 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{Sample, Sweep, TestTheoryResult};
+
+const CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// Prefix of the CSV columns holding the parameters, one per (nested) field.
+const PARAMETERS_COLUMN: &str = "parameters";
+
+/// Columns describing the run after the parameters, followed by the component name.
+const RUN_COLUMNS: [&str; 5] = ["seed", "threads", "samples", "time_elapsed", "component"];
+
+/// Columns of per-component statistics, in the order of [`Statistic::values`].
+const STATISTIC_COLUMNS: [&str; 8] = [
+    "theory",
+    "empirical",
+    "variance",
+    "standard_error",
+    "ci95_lower",
+    "ci95_upper",
+    "z_score",
+    "p_value",
+];
+
+/// Statistics of one component. `serde_json` writes NaN and infinities, which JSON cannot
+/// represent, as `null`.
+#[derive(Serialize)]
+struct Statistic {
+    component: String,
+    theory: f64,
+    empirical: f64,
+    variance: f64,
+    standard_error: f64,
+    ci95_lower: f64,
+    ci95_upper: f64,
+    z_score: f64,
+    p_value: f64,
+}
+
+impl Statistic {
+    fn values(&self) -> [f64; 8] {
+        [
+            self.theory,
+            self.empirical,
+            self.variance,
+            self.standard_error,
+            self.ci95_lower,
+            self.ci95_upper,
+            self.z_score,
+            self.p_value,
+        ]
+    }
+}
+
+fn statistics<S: Sample>(result: &TestTheoryResult<S>) -> Vec<Statistic> {
+    let (lower, upper) = result.confidence_interval(CONFIDENCE_LEVEL);
+    let columns = [
+        result.theoretical_result.components(),
+        result.empirical_mean.components(),
+        result.empirical_variance.components(),
+        result.standard_error().components(),
+        lower.components(),
+        upper.components(),
+        result.z_score().components(),
+        result.p_value().components(),
+    ];
+    result
+        .theoretical_result
+        .component_names()
+        .into_iter()
+        .enumerate()
+        .map(|(index, component)| {
+            let value = |column: usize| columns[column][index];
+            Statistic {
+                component,
+                theory: value(0),
+                empirical: value(1),
+                variance: value(2),
+                standard_error: value(3),
+                ci95_lower: value(4),
+                ci95_upper: value(5),
+                z_score: value(6),
+                p_value: value(7),
+            }
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct ResultJson<'a> {
+    parameters: &'a Value,
+    seed: u64,
+    threads: usize,
+    samples: u64,
+    time_elapsed: f64,
+    statistics: Vec<Statistic>,
+}
+
+impl<'a> ResultJson<'a> {
+    fn new<S: Sample>(result: &'a TestTheoryResult<S>) -> Self {
+        Self {
+            parameters: &result.parameter_values,
+            seed: result.seed,
+            threads: result.threads,
+            samples: result.samples,
+            time_elapsed: result.time_elapsed.as_secs_f64(),
+            statistics: statistics(result),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PointJson<'a> {
+    values: Map<String, Value>,
+    seed: u64,
+    result: ResultJson<'a>,
+}
+
+#[derive(Serialize)]
+struct SweepJson<'a> {
+    axes: &'a [&'static str],
+    points: Vec<PointJson<'a>>,
+}
+
+/// Swept values that look like numbers are written as JSON numbers, everything else as strings.
+fn json_label(label: &str) -> Value {
+    label
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map_or_else(|| Value::String(label.to_string()), Value::Number)
+}
+
+/// Flattens the parameters into one `(column, value)` pair per scalar, naming nested fields and
+/// elements with dots, e.g. `parameters.model_parameters.service_time`.
+fn parameter_columns(name: String, value: &Value, columns: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(fields) => {
+            for (field, value) in fields {
+                parameter_columns(format!("{name}.{field}"), value, columns);
+            }
+        }
+        Value::Array(elements) => {
+            for (index, value) in elements.iter().enumerate() {
+                parameter_columns(format!("{name}.{index}"), value, columns);
+            }
+        }
+        Value::Null => columns.push((name, String::new())),
+        Value::String(string) => columns.push((name, string.clone())),
+        scalar => columns.push((name, scalar.to_string())),
+    }
+}
+
+fn flat_parameters<S: Sample>(result: &TestTheoryResult<S>) -> Vec<(String, String)> {
+    let mut columns = Vec::new();
+    parameter_columns(
+        PARAMETERS_COLUMN.to_string(),
+        &result.parameter_values,
+        &mut columns,
+    );
+    columns
+}
+
+/// Writes one line per component of each result, after the given labels. Parameter columns are
+/// the union over all results, left empty where a result has no such field.
+fn write_csv<'a, S: Sample + 'a>(
+    label_columns: &[&str],
+    rows: impl IntoIterator<Item = (&'a [String], &'a TestTheoryResult<S>)> + Clone,
+) -> String {
+    let mut parameter_names: Vec<String> = Vec::new();
+    for (_, result) in rows.clone() {
+        for (name, _) in flat_parameters(result) {
+            if !parameter_names.contains(&name) {
+                parameter_names.push(name);
+            }
+        }
+    }
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let header = label_columns
+        .iter()
+        .copied()
+        .chain(parameter_names.iter().map(String::as_str))
+        .chain(RUN_COLUMNS)
+        .chain(STATISTIC_COLUMNS);
+    writer.write_record(header).unwrap();
+    for (labels, result) in rows {
+        let parameters = flat_parameters(result);
+        let parameter_fields = parameter_names.iter().map(|name| {
+            parameters
+                .iter()
+                .find(|(other, _)| other == name)
+                .map_or(String::new(), |(_, value)| value.clone())
+        });
+        for statistic in statistics(result) {
+            let record = labels
+                .iter()
+                .cloned()
+                .chain(parameter_fields.clone())
+                .chain([
+                    result.seed.to_string(),
+                    result.threads.to_string(),
+                    result.samples.to_string(),
+                    result.time_elapsed.as_secs_f64().to_string(),
+                    statistic.component.clone(),
+                ])
+                .chain(statistic.values().map(|value| value.to_string()));
+            writer.write_record(record).unwrap();
+        }
+    }
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+impl<S> TestTheoryResult<S>
+where
+    S: Sample,
+{
+    /// CSV with a header line and one line per component, starting with one `parameters.<field>`
+    /// column per parameter. Times are in seconds, confidence intervals at the 95% level, and
+    /// non-finite numbers are written as `NaN`, `inf` and `-inf`.
+    pub fn to_csv(&self) -> String {
+        write_csv(&[], [(&[][..], self)])
+    }
+
+    /// A JSON object with the serialized `parameters`, the run metadata and a `statistics` array
+    /// with one entry per component. Times are in seconds and non-finite numbers are `null`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&ResultJson::new(self)).unwrap()
+    }
+}
+
+impl<X, S> Sweep<X, S>
+where
+    S: Sample,
+{
+    /// CSV with one column per swept axis followed by the columns of
+    /// [`TestTheoryResult::to_csv`], and one line per point and component.
+    pub fn to_csv(&self) -> String {
+        write_csv(
+            self.axes(),
+            self.points()
+                .iter()
+                .map(|point| (point.labels(), point.result())),
+        )
+    }
+
+    /// A JSON object with the `axes` and a `points` array, each point holding the swept `values`,
+    /// its `seed` and the `result` in the format of [`TestTheoryResult::to_json`].
+    pub fn to_json(&self) -> String {
+        let points = self
+            .points()
+            .iter()
+            .map(|point| PointJson {
+                values: self
+                    .axes()
+                    .iter()
+                    .zip(point.labels())
+                    .map(|(axis, label)| (axis.to_string(), json_label(label)))
+                    .collect(),
+                seed: point.seed(),
+                result: ResultJson::new(point.result()),
+            })
+            .collect();
+        serde_json::to_string(&SweepJson {
+            axes: self.axes(),
+            points,
+        })
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::Rng;
+    use rand_pcg::Pcg64Mcg;
+
+    use super::*;
+    use crate::{sweep, ExperimentRng, Moments, Record};
+
+    #[derive(Debug, Serialize)]
+    struct Inner {
+        rate: f64,
+        units: Option<u64>,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct Parameters {
+        label: String,
+        inner: Inner,
+        weights: [f64; 2],
+    }
+
+    const LABEL: &str = "say \"hi\", then\nleave";
+
+    fn parameters() -> Parameters {
+        Parameters {
+            label: LABEL.to_string(),
+            inner: Inner {
+                rate: 0.25,
+                units: None,
+            },
+            weights: [1., 2.],
+        }
+    }
+
+    /// A result with non-finite statistics and a component name that needs quoting.
+    fn result() -> TestTheoryResult<Record<2>> {
+        let record = |first, second| Record::new([("mean, total", first), ("rate", second)]);
+        TestTheoryResult::from_moments(
+            &parameters(),
+            7,
+            record(f64::INFINITY, 1.),
+            Moments::new(4, record(2., f64::NAN), record(1., 0.)),
+            Duration::from_millis(1500),
+        )
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let csv = result().to_csv();
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let header: Vec<_> = reader.headers().unwrap().iter().map(String::from).collect();
+        assert_eq!(
+            header[..6],
+            [
+                "parameters.label",
+                "parameters.inner.rate",
+                "parameters.inner.units",
+                "parameters.weights.0",
+                "parameters.weights.1",
+                "seed"
+            ]
+        );
+        let records: Vec<_> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 2);
+        let field = |row: usize, column: &str| {
+            let index = header.iter().position(|name| name == column).unwrap();
+            records[row][index].to_string()
+        };
+        let number = |row, column| field(row, column).parse::<f64>().unwrap();
+        assert_eq!(field(0, "parameters.label"), LABEL);
+        assert_eq!(number(0, "parameters.inner.rate"), 0.25);
+        assert_eq!(field(0, "parameters.inner.units"), "");
+        assert_eq!(number(1, "parameters.weights.1"), 2.);
+        assert_eq!(field(0, "seed"), "7");
+        assert_eq!(number(0, "time_elapsed"), 1.5);
+        assert_eq!(field(0, "component"), "mean, total");
+        assert_eq!(number(0, "theory"), f64::INFINITY);
+        assert_eq!(number(0, "empirical"), 2.);
+        assert_eq!(number(0, "variance"), 1.);
+        assert_eq!(number(0, "standard_error"), 0.5);
+        assert!(number(1, "empirical").is_nan());
+        assert_eq!(number(0, "z_score"), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json: Value = serde_json::from_str(&result().to_json()).unwrap();
+        assert_eq!(json["parameters"]["label"], LABEL);
+        assert_eq!(json["parameters"]["inner"]["rate"], 0.25);
+        assert!(json["parameters"]["inner"]["units"].is_null());
+        assert_eq!(json["parameters"]["weights"], serde_json::json!([1., 2.]));
+        assert_eq!(json["seed"], 7);
+        assert_eq!(json["samples"], 4);
+        assert_eq!(json["time_elapsed"], 1.5);
+        let statistics = json["statistics"].as_array().unwrap();
+        assert_eq!(statistics.len(), 2);
+        assert_eq!(statistics[0]["component"], "mean, total");
+        assert!(statistics[0]["theory"].is_null());
+        assert_eq!(statistics[0]["empirical"], 2.);
+        assert!(statistics[1]["empirical"].is_null());
+        assert_eq!(statistics[1]["theory"], 1.);
+    }
+
+    #[derive(Debug, Serialize)]
+    struct Rate {
+        rate: f64,
+    }
+
+    #[test]
+    fn sweep_round_trip() {
+        let sweep = sweep(
+            |&Rate { rate }: &Rate, rng: &mut ExperimentRng| rate * rng.gen::<f64>(),
+            |&Rate { rate }: &Rate| rate / 2.,
+            "rate, per hour",
+            ["1.5", "fast"],
+            |&label| Rate {
+                rate: label.parse().unwrap_or(10.),
+            },
+            10,
+            &mut Pcg64Mcg::new(3),
+        );
+
+        let csv = sweep.to_csv();
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let header: Vec<_> = reader.headers().unwrap().iter().map(String::from).collect();
+        assert_eq!(header[..2], ["rate, per hour", "parameters.rate"]);
+        let records: Vec<_> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(&records[0][0], "1.5");
+        assert_eq!(&records[1][0], "fast");
+        assert_eq!(records[1][1].parse::<f64>().unwrap(), 10.);
+        for (record, point) in records.iter().zip(sweep.points()) {
+            let seed = header.iter().position(|name| name == "seed").unwrap();
+            assert_eq!(record[seed].parse::<u64>().unwrap(), point.result().seed());
+        }
+
+        let json: Value = serde_json::from_str(&sweep.to_json()).unwrap();
+        assert_eq!(json["axes"], serde_json::json!(["rate, per hour"]));
+        let points = json["points"].as_array().unwrap();
+        assert_eq!(points[0]["values"]["rate, per hour"], 1.5);
+        assert_eq!(points[1]["values"]["rate, per hour"], "fast");
+        assert_eq!(points[1]["seed"], sweep.points()[1].seed());
+        assert_eq!(points[1]["result"]["parameters"]["rate"], 10.);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    linalg,
+    stationary::{validate_subgenerator, validate_substochastic},
+    Matrix, Vector,
+};
+
+/// A Markov chain given by its matrix.
+#[derive(Debug, Clone, Copy)]
+pub enum ChainMatrix<'a> {
+    /// Transition probabilities of a discrete-time chain. Rows may sum to less than one, the
+    /// missing probability leading to an implicit absorbing state.
+    Transition(&'a Matrix),
+    /// Generator of a continuous-time chain. Rows may sum to less than zero, see
+    /// [`crate::MatrixTransitions`].
+    Generator(&'a Matrix),
+}
+
+impl ChainMatrix<'_> {
+    fn matrix(&self) -> &Matrix {
+        match self {
+            ChainMatrix::Transition(matrix) | ChainMatrix::Generator(matrix) => matrix,
+        }
+    }
+
+    fn validate(&self) {
+        match self {
+            ChainMatrix::Transition(transitions) => validate_substochastic(transitions),
+            ChainMatrix::Generator(generator) => validate_subgenerator(generator),
+        }
+    }
+
+    /// Whether the chain can leave `state` for the implicit absorbing state.
+    fn leaks(&self, state: usize) -> bool {
+        let sum = self.matrix().row(state).sum();
+        match self {
+            ChainMatrix::Transition(_) => sum < 1. - 1e-9,
+            ChainMatrix::Generator(generator) => {
+                sum < -1e-9 * generator[[state, state]].abs().max(1.)
+            }
+        }
+    }
+
+    /// `I - P` or `-Q` restricted to `states`, the matrix of the first passage equations.
+    fn first_passage_system(&self, states: &[usize]) -> Matrix {
+        Matrix::from_shape_fn((states.len(), states.len()), |(r, c)| {
+            let (i, j) = (states[r], states[c]);
+            match self {
+                ChainMatrix::Transition(transitions) => {
+                    if i == j {
+                        1. - transitions[[i, j]]
+                    } else {
+                        -transitions[[i, j]]
+                    }
+                }
+                ChainMatrix::Generator(generator) => -generator[[i, j]],
+            }
+        })
+    }
+}
+
+fn indicator(num_states: usize, states: &[usize]) -> Vec<bool> {
+    let mut indicator = vec![false; num_states];
+    for &state in states {
+        assert!(
+            state < num_states,
+            "Invalid state {state}. Maximum state is {}",
+            num_states - 1
+        );
+        indicator[state] = true;
+    }
+    indicator
+}
+
+/// States from which the chain, stopped in the states marked `stopped`, can reach a state marked
+/// `to`. States in `to` are included.
+fn can_reach(matrix: &Matrix, stopped: &[bool], to: &[bool]) -> Vec<bool> {
+    let mut reached = to.to_vec();
+    let mut stack: Vec<_> = (0..to.len()).filter(|&j| to[j]).collect();
+    while let Some(j) = stack.pop() {
+        for (i, &entry) in matrix.column(j).iter().enumerate() {
+            if i != j && entry > 0. && !stopped[i] && !reached[i] {
+                reached[i] = true;
+                stack.push(i);
+            }
+        }
+    }
+    reached
+}
+
+/// Probabilities, for each state, that the first of the disjoint `target_sets` the chain hits is
+/// the set in the corresponding column. States in a target set hit it at time zero.
+pub fn absorption_probabilities(chain: ChainMatrix, target_sets: &[&[usize]]) -> Matrix {
+    chain.validate();
+    let matrix = chain.matrix();
+    let n = matrix.nrows();
+    let sets: Vec<_> = target_sets.iter().map(|set| indicator(n, set)).collect();
+    let targets: Vec<_> = (0..n)
+        .map(|i| {
+            let count = sets.iter().filter(|set| set[i]).count();
+            assert!(
+                count <= 1,
+                "Target sets must be disjoint. State {i} is in {count}"
+            );
+            count == 1
+        })
+        .collect();
+
+    let mut probabilities = Matrix::zeros((n, sets.len()));
+    for (k, set) in sets.iter().enumerate() {
+        for i in (0..n).filter(|&i| set[i]) {
+            probabilities[[i, k]] = 1.;
+        }
+    }
+    let reaches_target = can_reach(matrix, &targets, &targets);
+    let states: Vec<_> = (0..n)
+        .filter(|&i| reaches_target[i] && !targets[i])
+        .collect();
+    if states.is_empty() {
+        return probabilities;
+    }
+    let rhs = Matrix::from_shape_fn((states.len(), sets.len()), |(r, k)| {
+        (0..n)
+            .filter(|&j| sets[k][j])
+            .map(|j| matrix[[states[r], j]])
+            .sum()
+    });
+    let solution = linalg::solve_matrix(&chain.first_passage_system(&states), &rhs);
+    for (r, &i) in states.iter().enumerate() {
+        probabilities.row_mut(i).assign(&solution.row(r));
+    }
+    probabilities
+}
+
+/// Probability, for each state, that the chain ever hits `targets`.
+pub fn hitting_probabilities(chain: ChainMatrix, targets: &[usize]) -> Vector {
+    absorption_probabilities(chain, &[targets])
+        .column(0)
+        .to_owned()
+}
+
+/// The first `order` moments of the time until the chain hits `targets`, counted in steps for a
+/// discrete-time chain. Entry `k` holds the `k + 1`-th moment for each state.
+///
+/// Moments are infinite for states from which the chain may never hit `targets`.
+pub fn hitting_time_moments(chain: ChainMatrix, targets: &[usize], order: usize) -> Vec<Vector> {
+    chain.validate();
+    let matrix = chain.matrix();
+    let n = matrix.nrows();
+    let targets = indicator(n, targets);
+    let reaches_target = can_reach(matrix, &targets, &targets);
+    let escapes: Vec<_> = (0..n)
+        .map(|i| !targets[i] && (!reaches_target[i] || chain.leaks(i)))
+        .collect();
+    let may_escape = can_reach(matrix, &targets, &escapes);
+    let states: Vec<_> = (0..n).filter(|&i| !targets[i] && !may_escape[i]).collect();
+
+    let system = chain.first_passage_system(&states);
+    let mut moments: Vec<Vector> = Vec::with_capacity(order);
+    for k in 1..=order {
+        let rhs = match chain {
+            ChainMatrix::Transition(transitions) => Vector::from_shape_fn(states.len(), |r| {
+                // (1 + T')^k expanded, T' being the remaining time after the first step.
+                1. + (1..k)
+                    .map(|j| {
+                        let binomial = num_integer::binomial(k as u64, j as u64) as f64;
+                        let expected: f64 = states
+                            .iter()
+                            .enumerate()
+                            .map(|(c, &l)| transitions[[states[r], l]] * moments[j - 1][c])
+                            .sum();
+                        binomial * expected
+                    })
+                    .sum::<f64>()
+            }),
+            ChainMatrix::Generator(_) => match moments.last() {
+                None => Vector::ones(states.len()),
+                Some(previous) => previous * k as f64,
+            },
+        };
+        moments.push(linalg::solve(&system, &rhs));
+    }
+
+    moments
+        .into_iter()
+        .map(|restricted| {
+            let mut moment =
+                Vector::from_shape_fn(n, |i| if targets[i] { 0. } else { f64::INFINITY });
+            for (&i, &value) in states.iter().zip(&restricted) {
+                moment[i] = value;
+            }
+            moment
+        })
+        .collect()
+}
+
+/// Expected time until the chain hits `targets` from each state, counted in steps for a
+/// discrete-time chain. Infinite for states from which the chain may never hit `targets`.
+pub fn expected_hitting_times(chain: ChainMatrix, targets: &[usize]) -> Vector {
+    hitting_time_moments(chain, targets, 1).remove(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::{test_util::assert_close, BirthAndDeathProbabilities, MarkovQueueProbabilities};
+
+    /// Symmetric random walk on `0..=n` absorbed at both ends.
+    fn gamblers_ruin(n: usize) -> Matrix {
+        Matrix::from_shape_fn((n + 1, n + 1), |(i, j)| {
+            if i == 0 || i == n {
+                (i == j) as u8 as f64
+            } else if i.abs_diff(j) == 1 {
+                0.5
+            } else {
+                0.
+            }
+        })
+    }
+
+    #[test]
+    fn gamblers_ruin_absorption() {
+        let n = 8;
+        let transitions = gamblers_ruin(n);
+        let probabilities =
+            absorption_probabilities(ChainMatrix::Transition(&transitions), &[&[0], &[n]]);
+        for i in 0..=n {
+            assert_close(probabilities[[i, 1]], i as f64 / n as f64);
+            assert_close(probabilities[[i, 0]], 1. - i as f64 / n as f64);
+        }
+    }
+
+    #[test]
+    fn gamblers_ruin_duration() {
+        let n = 8;
+        let transitions = gamblers_ruin(n);
+        let moments = hitting_time_moments(ChainMatrix::Transition(&transitions), &[0, n], 2);
+        for i in 0..=n {
+            let (i, n) = (i as f64, n as f64);
+            let mean = i * (n - i);
+            let variance = i * (n - i) * ((n - i).powi(2) + i.powi(2) - 2.) / 3.;
+            assert_close(moments[0][i as usize], mean);
+            assert_close(moments[1][i as usize], variance + mean * mean);
+        }
+    }
+
+    #[test]
+    fn mm1_busy_period_moments() {
+        // From k customers the time to empty is a sum of k independent busy periods, with mean
+        // 1 / (μ - λ) and second moment 2 / (μ² (1 - ρ)³).
+        let (arrival_rate, service_rate) = (1., 2.);
+        let generator = MarkovQueueProbabilities::with_capacity(arrival_rate, service_rate, 1, 199)
+            .truncated_generator(200);
+        let moments = hitting_time_moments(ChainMatrix::Generator(&generator), &[0], 2);
+        let rho: f64 = arrival_rate / service_rate;
+        let mean = 1. / (service_rate - arrival_rate);
+        let second_moment = 2. / (service_rate.powi(2) * (1. - rho).powi(3));
+        let variance = second_moment - mean * mean;
+        for (k, (&first, &second)) in moments[0].iter().zip(&moments[1]).take(5).enumerate() {
+            let k = k as f64;
+            assert_close(first, k * mean);
+            assert_close(second, k * variance + (k * mean).powi(2));
+        }
+    }
+
+    #[test]
+    fn hitting_times_are_infinite_when_targets_may_be_missed() {
+        // State 1 leaks to the implicit absorbing state.
+        let generator = array![[-1., 1., 0.], [1., -3., 1.], [0., 0., 0.]];
+        let times = expected_hitting_times(ChainMatrix::Generator(&generator), &[2]);
+        assert_eq!(times[0], f64::INFINITY);
+        assert_eq!(times[1], f64::INFINITY);
+        assert_eq!(times[2], 0.);
+        let probabilities = hitting_probabilities(ChainMatrix::Generator(&generator), &[2]);
+        assert_close(probabilities[0], 0.5);
+    }
+}
@@ -1,18 +1,71 @@
 use std::{
-    fmt::Display,
-    ops::{Add, Div}, time::{Instant, Duration},
+    fmt::{self, Debug, Display},
+    time::{Duration, Instant},
 };
 
-use ndarray::{Array1, Array2};
+use ndarray::{Array, Array1, Array2, Dimension};
 use rand::{Rng, SeedableRng};
 use rand_pcg::Pcg64Mcg;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
+use statrs::distribution::{ContinuousCDF, Normal};
 
+mod birth_death;
+pub use birth_death::BirthDeathAnalysis;
 mod brownian_motion;
 pub use brownian_motion::{BrownianMotion, GeometricBrownianMotion};
+mod comparison;
+pub use comparison::{Comparison, ComparisonRow};
 mod continuous_markov_process;
-pub use continuous_markov_process::{ContinuousMarkovTransitions, ContinuousMarkovProcess, MarkovQueueProbabilities, BirthAndDeathProbabilities, MatrixTransitions};
+pub use continuous_markov_process::{
+    BirthAndDeathProbabilities, ContinuousMarkovProcess, ContinuousMarkovTransitions,
+    FinitePopulationProbabilities, InhomogeneousMarkovQueueProbabilities, MarkovQueueProbabilities,
+    MatrixTransitions, Stop, StopReason, TimeDependentBirthAndDeath,
+};
+mod discrete_markov_chain;
+pub use discrete_markov_chain::{
+    ClosureTransitions, DiscreteMarkovChain, DiscreteMarkovTransitions, TransitionMatrix,
+};
+mod export;
+mod hitting;
+pub use hitting::{
+    absorption_probabilities, expected_hitting_times, hitting_probabilities, hitting_time_moments,
+    ChainMatrix,
+};
+mod linalg;
+mod queue_log;
+pub use queue_log::{Customer, EventLog, QueueEvent, QueueEventKind};
 mod queue_system;
+pub use queue_system::{
+    GeneralQueueSystem, MarkovServiceQueueSystem, QueueDiscipline, QueueSystem,
+};
+mod reaction_network;
+pub use reaction_network::{NextReactionMethod, Reaction, ReactionNetwork};
+mod record;
+pub use record::Record;
+mod sample;
+pub use sample::{Accumulator, Moments, Sample, WelfordAccumulator};
+mod sparse_transitions;
+pub use sparse_transitions::SparseTransitions;
+mod stationary;
+pub use stationary::{
+    communicating_classes, deviation_matrix, stationary_distribution, stationary_distributions,
+    validate_generator, CommunicatingClass, StationaryDistribution,
+};
+mod stochastic_process;
+pub use stochastic_process::{Events, StochasticProcess};
+mod sweep;
+pub use sweep::{sweep, sweep_grid, Sweep, SweepPoint};
+#[cfg(test)]
+mod test_util;
+mod trajectory;
+pub use trajectory::Trajectory;
+mod transient;
+pub use transient::{
+    expected_accumulated_reward, expm, transient_distribution, transient_distributions,
+    transition_probabilities,
+};
+mod window;
 
 pub type Vector = Array1<f64>;
 pub type Matrix = Array2<f64>;
@@ -23,66 +76,69 @@ pub fn factorial(n: u64) -> u64 {
     (1..=n).product()
 }
 
-pub trait Sample:
-    Sized + Add<Self, Output = Self> + Div<f64, Output = Self> + Send + Sync + Display
-{
-    fn mean<I>(iter: I) -> Self
-    where
-        I: IntoIterator<Item = Self>;
-}
+/// Number of samples drawn from each seed.
+///
+/// Work is split into chunks of this size independently of the number of threads, so results only
+/// depend on the master rng.
+const CHUNK_SIZE: u64 = 1_000;
 
-impl<T> Sample for T
-where
-    T: Sized + Add<Self, Output = Self> + Div<f64, Output = Self> + Send + Sync + Display,
-{
-    fn mean<I>(iter: I) -> Self
-    where
-        I: IntoIterator<Item = Self>,
-    {
-        let mut iter = iter.into_iter();
-
-        let mut sum = iter.next().unwrap();
-        let mut count = 1;
-        for x in iter {
-            sum = sum + x;
-            count += 1;
-        }
-        sum / count as f64
-    }
-}
+/// Number of chunks run in parallel before their accumulators are merged into the running total.
+const CHUNKS_PER_WAVE: u64 = 256;
 
+/// Runs exactly `num_samples` samples of the experiment on the current rayon thread pool.
+///
+/// Each chunk of [`CHUNK_SIZE`] samples gets its own seed drawn in order from `rng`, and chunks are
+/// merged in order, so the result is bit-identical regardless of how many threads the pool has.
 fn run_experiment<P, E, S>(
-    experiment: E,
+    experiment: &E,
     parameters: &P,
-    num_samples: u32,
-    max_threads: u32,
+    num_samples: u64,
     rng: &mut impl Rng,
-) -> S
+) -> S::Accumulator
 where
     P: Sync,
     E: Fn(&P, &mut ExperimentRng) -> S + Sync + Send,
     S: Sample,
 {
-    let num_samples_per_thread = num_samples / max_threads;
-    let seeds: Vec<_> = (0..max_threads).map(|_| rng.next_u64()).collect();
-
-    let total: Vec<_> = seeds
-        .into_par_iter()
-        .map(|seed| {
-            let mut rng = ExperimentRng::seed_from_u64(seed);
-            let experiment = &experiment;
-            (0..num_samples_per_thread).map(move |_| experiment(parameters, &mut rng))
-        })
-        .flatten_iter()
-        .collect();
-
-    S::mean(total)
+    assert!(num_samples > 0, "At least one sample is needed.");
+    let num_chunks = num_samples.div_ceil(CHUNK_SIZE);
+    let mut accumulator = S::Accumulator::zero();
+    for wave_start in (0..num_chunks).step_by(CHUNKS_PER_WAVE as usize) {
+        let wave_end = (wave_start + CHUNKS_PER_WAVE).min(num_chunks);
+        let chunks: Vec<_> = (wave_start..wave_end)
+            .map(|chunk| {
+                let chunk_size = (num_samples - chunk * CHUNK_SIZE).min(CHUNK_SIZE);
+                (rng.next_u64(), chunk_size)
+            })
+            .collect();
+        let wave: Vec<_> = chunks
+            .into_par_iter()
+            .map(|(seed, chunk_size)| {
+                let mut rng = ExperimentRng::seed_from_u64(seed);
+                let mut chunk_accumulator = S::Accumulator::zero();
+                for _ in 0..chunk_size {
+                    chunk_accumulator.push(&experiment(parameters, &mut rng));
+                }
+                chunk_accumulator
+            })
+            .collect();
+        for chunk_accumulator in wave {
+            accumulator.merge(chunk_accumulator);
+        }
+    }
+    accumulator
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct TestTheoryResult<S: Sample> {
+    parameters: String,
+    parameter_values: serde_json::Value,
+    seed: u64,
+    threads: usize,
     theoretical_result: S,
     empirical_mean: S,
+    empirical_variance: S,
+    samples: u64,
     time_elapsed: Duration,
 }
 
@@ -94,9 +150,177 @@ where
         (&self.theoretical_result, &self.empirical_mean)
     }
 
+    /// Unbiased sample variance of the experiment. Component-wise for array samples.
+    pub fn empirical_variance(&self) -> &S {
+        &self.empirical_variance
+    }
+
     pub fn time_elapsed(&self) -> Duration {
         self.time_elapsed
     }
+
+    /// Number of samples the empirical statistics are based on.
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// The parameters of the run, formatted with `Debug`.
+    pub fn parameters(&self) -> &str {
+        &self.parameters
+    }
+
+    /// The parameters of the run as serialized by `serde`, e.g. an object with one entry per field.
+    pub fn parameter_values(&self) -> &serde_json::Value {
+        &self.parameter_values
+    }
+
+    /// The per-chunk seeds of the run are drawn from `ExperimentRng::seed_from_u64(seed)`.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Number of threads in the rayon pool the run was executed on.
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    fn from_moments(
+        parameters: &(impl Debug + Serialize),
+        seed: u64,
+        theoretical_result: S,
+        moments: Moments<S>,
+        time_elapsed: Duration,
+    ) -> Self {
+        let (samples, empirical_mean, empirical_variance) = moments.into_parts();
+        assert_eq!(
+            theoretical_result.component_names(),
+            empirical_mean.component_names(),
+            "Theory and experiment must produce samples with the same components."
+        );
+        TestTheoryResult {
+            parameters: format!("{parameters:?}"),
+            parameter_values: serde_json::to_value(parameters)
+                .expect("Parameters must serialize to JSON, e.g. maps need string keys."),
+            seed,
+            threads: rayon::current_num_threads(),
+            theoretical_result,
+            empirical_mean,
+            empirical_variance,
+            samples,
+            time_elapsed,
+        }
+    }
+}
+
+impl<S> TestTheoryResult<S>
+where
+    S: Sample,
+{
+    /// Estimated standard deviation of the empirical mean, per component.
+    pub fn standard_error(&self) -> S {
+        let samples = self.samples as f64;
+        self.empirical_variance
+            .map_components(|variance| (variance / samples).sqrt())
+    }
+
+    /// Normal approximation confidence interval for the true mean at the given level, e.g. `0.95`.
+    ///
+    /// For array samples the interval is per component and not corrected for multiple comparisons.
+    pub fn confidence_interval(&self, level: f64) -> (S, S) {
+        let half_width = self.half_width(level);
+        (
+            self.empirical_mean
+                .zip_components(&half_width, |mean, half_width| mean - half_width),
+            self.empirical_mean
+                .zip_components(&half_width, |mean, half_width| mean + half_width),
+        )
+    }
+
+    /// Half the width of the confidence interval at the given level.
+    pub fn half_width(&self, level: f64) -> S {
+        assert!(
+            level > 0. && level < 1.,
+            "Confidence level must be in (0, 1). Got {level}"
+        );
+        let quantile = normal_quantile(0.5 + level / 2.);
+        self.standard_error()
+            .map_components(|standard_error| quantile * standard_error)
+    }
+
+    /// Number of standard errors the empirical mean lies from the theoretical result.
+    pub fn z_score(&self) -> S {
+        self.empirical_mean
+            .zip_components(&self.theoretical_result, |mean, theory| mean - theory)
+            .zip_components(&self.standard_error(), |difference, standard_error| {
+                difference / standard_error
+            })
+    }
+
+    /// Two-sided p-value for the hypothesis that the theoretical result is the true mean.
+    pub fn p_value(&self) -> S {
+        self.z_score()
+            .map_components(|z_score| 2. * (1. - standard_normal().cdf(z_score.abs())))
+    }
+
+    /// Per-component report flagging where the theory is rejected at the given significance level.
+    pub fn comparison(&self, significance: f64) -> Comparison {
+        Comparison::new(
+            self.theoretical_result.component_names(),
+            self.theoretical_result.components(),
+            self.empirical_mean.components(),
+            self.standard_error().components(),
+            self.p_value().components(),
+            significance,
+        )
+    }
+}
+
+impl Display for TestTheoryResult<f64> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (lower, upper) = self.confidence_interval(0.95);
+        writeln!(f, "theory:         {}", self.theoretical_result)?;
+        writeln!(f, "empirical:      {}", self.empirical_mean)?;
+        writeln!(f, "standard error: {}", self.standard_error())?;
+        writeln!(f, "95% CI:         [{lower}, {upper}]")?;
+        writeln!(f, "z-score:        {}", self.z_score())?;
+        writeln!(f, "p-value:        {}", self.p_value())?;
+        writeln!(f, "samples:        {}", self.samples)?;
+        write!(f, "time elapsed:   {:?}", self.time_elapsed)
+    }
+}
+
+impl<S> TestTheoryResult<S>
+where
+    S: Sample,
+{
+    fn fmt_comparison(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.comparison(0.05))?;
+        writeln!(f, "samples:        {}", self.samples)?;
+        write!(f, "time elapsed:   {:?}", self.time_elapsed)
+    }
+}
+
+impl<D> Display for TestTheoryResult<Array<f64, D>>
+where
+    D: Dimension,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_comparison(f)
+    }
+}
+
+impl<const N: usize> Display for TestTheoryResult<Record<N>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_comparison(f)
+    }
+}
+
+fn standard_normal() -> Normal {
+    Normal::new(0., 1.).unwrap()
+}
+
+fn normal_quantile(probability: f64) -> f64 {
+    standard_normal().inverse_cdf(probability)
 }
 
 pub fn test_theory<P, E, S, T, R>(
@@ -104,33 +328,213 @@ pub fn test_theory<P, E, S, T, R>(
     theory: T,
     parameters: &P,
     samples: u32,
-    max_threads: u32,
     rng: &mut R,
 ) -> TestTheoryResult<S>
 where
-    P: Sync,
+    P: Sync + Debug + Serialize,
     E: Fn(&P, &mut ExperimentRng) -> S + Sync + Send,
     S: Sample,
     T: Fn(&P) -> S,
     R: Rng,
 {
     let start_time = Instant::now();
-    let empirical_mean = run_experiment(experiment, parameters, samples, max_threads, rng);
+    let seed = rng.next_u64();
+    let mut seeds = ExperimentRng::seed_from_u64(seed);
+    let moments = run_experiment(&experiment, parameters, samples as u64, &mut seeds).finalize();
     let theoretical_result = theory(parameters);
     let end_time = Instant::now();
-    TestTheoryResult {
+    TestTheoryResult::from_moments(
+        parameters,
+        seed,
         theoretical_result,
-        empirical_mean,
-        time_elapsed: end_time - start_time,
+        moments,
+        end_time - start_time,
+    )
+}
+
+/// Target width of a confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tolerance {
+    /// Maximum half-width of the interval.
+    Absolute(f64),
+    /// Maximum half-width of the interval relative to the magnitude of the empirical mean, or to
+    /// `floor` if the mean is smaller. Without the floor a statistic with mean near zero, such as a
+    /// centred one, would never meet the tolerance: below `floor` it becomes the absolute tolerance
+    /// `tolerance * floor`. The floor must be positive.
+    Relative { tolerance: f64, floor: f64 },
+}
+
+impl Tolerance {
+    fn is_met<S: Sample>(&self, result: &TestTheoryResult<S>, level: f64) -> bool {
+        let half_widths = result.half_width(level).components();
+        let means = result.empirical_mean.components();
+        half_widths
+            .into_iter()
+            .zip(means)
+            .all(|(half_width, mean)| match *self {
+                Tolerance::Absolute(tolerance) => half_width <= tolerance,
+                Tolerance::Relative { tolerance, floor } => {
+                    assert!(floor > 0., "Floor must be positive. Got {floor}");
+                    half_width <= tolerance * mean.abs().max(floor)
+                }
+            })
     }
 }
 
-#[macro_export]
-macro_rules! linux {
-    ($exp:expr) => {
-        #[cfg(target_os = "linux")]
-        $exp
-        #[cfg(not(target_os = "linux"))]
-        unimplemented!("Can only run on linux.")
-    };
+/// Stopping rule for [`test_theory_to_precision`].
+///
+/// Sampling stops as soon as the confidence interval is within `tolerance`, or when either
+/// `max_samples` or `max_time` is exceeded, whichever comes first.
+#[derive(Debug, Clone, Copy)]
+pub struct Precision {
+    pub tolerance: Tolerance,
+    pub confidence_level: f64,
+    /// Samples run between checks of the stopping rule. A batch is split into chunks of 1000
+    /// samples run in parallel, so a batch smaller than 1000 times the number of threads leaves
+    /// threads idle. The default is one full wave of 256 chunks.
+    pub batch_size: u32,
+    pub max_samples: u64,
+    pub max_time: Duration,
+}
+
+impl Precision {
+    /// Whether every component of the result is within tolerance.
+    pub fn is_reached<S: Sample>(&self, result: &TestTheoryResult<S>) -> bool {
+        self.tolerance.is_met(result, self.confidence_level)
+    }
+}
+
+impl Default for Precision {
+    fn default() -> Self {
+        Self {
+            tolerance: Tolerance::Relative {
+                tolerance: 0.01,
+                floor: 1e-3,
+            },
+            confidence_level: 0.95,
+            batch_size: (CHUNKS_PER_WAVE * CHUNK_SIZE) as u32,
+            max_samples: 100_000_000,
+            max_time: Duration::from_secs(600),
+        }
+    }
+}
+
+/// Like [`test_theory`], but runs batches of samples until the confidence interval of the
+/// empirical mean satisfies `precision`.
+///
+/// Check [`Precision::is_reached`] on the result to tell whether the tolerance was met or one of
+/// the caps stopped the run.
+pub fn test_theory_to_precision<P, E, S, T, R>(
+    experiment: E,
+    theory: T,
+    parameters: &P,
+    precision: &Precision,
+    rng: &mut R,
+) -> TestTheoryResult<S>
+where
+    P: Sync + Debug + Serialize,
+    E: Fn(&P, &mut ExperimentRng) -> S + Sync + Send,
+    S: Sample,
+    T: Fn(&P) -> S,
+    R: Rng,
+{
+    assert!(
+        precision.batch_size >= 2,
+        "Batch size must be at least 2. Batch size: {}",
+        precision.batch_size
+    );
+    let start_time = Instant::now();
+    let seed = rng.next_u64();
+    let mut seeds = ExperimentRng::seed_from_u64(seed);
+    let theoretical_result = theory(parameters);
+    let mut accumulator = run_experiment(
+        &experiment,
+        parameters,
+        precision.batch_size as u64,
+        &mut seeds,
+    );
+    loop {
+        let result = TestTheoryResult::from_moments(
+            parameters,
+            seed,
+            theoretical_result.clone(),
+            accumulator.finalize(),
+            start_time.elapsed(),
+        );
+        if precision.is_reached(&result)
+            || result.samples >= precision.max_samples
+            || result.time_elapsed >= precision.max_time
+        {
+            return result;
+        }
+        let batch_size = (precision.max_samples - result.samples).min(precision.batch_size as u64);
+        accumulator.merge(run_experiment(
+            &experiment,
+            parameters,
+            batch_size,
+            &mut seeds,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_pcg::Pcg64Mcg;
+
+    use super::*;
+
+    #[test]
+    fn relative_tolerance_is_met_for_zero_mean() {
+        let precision = Precision {
+            tolerance: Tolerance::Relative {
+                tolerance: 0.1,
+                floor: 0.1,
+            },
+            max_samples: 10_000_000,
+            ..Precision::default()
+        };
+        let result = test_theory_to_precision(
+            |_: &(), rng: &mut ExperimentRng| rng.gen_range(-1. ..1.),
+            |_| 0.,
+            &(),
+            &precision,
+            &mut Pcg64Mcg::new(0),
+        );
+        assert!(precision.is_reached(&result));
+        assert!(result.samples < precision.max_samples);
+    }
+
+    #[test]
+    fn results_do_not_depend_on_the_thread_count() {
+        // More chunks than fit in one wave, and a last chunk that is not full.
+        let samples = (CHUNKS_PER_WAVE * CHUNK_SIZE + 2_500) as u32;
+        let run = |threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| {
+                    test_theory(
+                        |_: &(), rng: &mut ExperimentRng| rng.gen::<f64>().powi(3),
+                        |_| 0.25,
+                        &(),
+                        samples,
+                        &mut Pcg64Mcg::new(1),
+                    )
+                })
+        };
+        let single = run(1);
+        let parallel = run(4);
+        assert_eq!(single.threads(), 1);
+        assert_eq!(parallel.threads(), 4);
+        assert_eq!(single.samples(), parallel.samples());
+        assert_eq!(
+            single.empirical_mean.to_bits(),
+            parallel.empirical_mean.to_bits()
+        );
+        assert_eq!(
+            single.empirical_variance.to_bits(),
+            parallel.empirical_variance.to_bits()
+        );
+    }
 }
@@ -0,0 +1,79 @@
+use ndarray::Axis;
+
+use crate::{Matrix, Vector};
+
+/// Solves `a x = b` for every column of `b` by Gaussian elimination with partial pivoting.
+///
+/// Panics if `a` is singular.
+pub(crate) fn solve_matrix(a: &Matrix, b: &Matrix) -> Matrix {
+    assert!(a.is_square(), "Matrix must be square");
+    assert_eq!(
+        a.nrows(),
+        b.nrows(),
+        "Right-hand side must have as many rows as the matrix"
+    );
+    let n = a.nrows();
+    let mut a = a.clone();
+    let mut x = b.clone();
+    let tolerance = 1e-12 * a.iter().fold(0., |max: f64, value| max.max(value.abs()));
+    for k in 0..n {
+        let pivot = (k..n)
+            .max_by(|&i, &j| a[[i, k]].abs().total_cmp(&a[[j, k]].abs()))
+            .unwrap();
+        assert!(a[[pivot, k]].abs() > tolerance, "Matrix is singular");
+        if pivot != k {
+            for j in 0..n {
+                a.swap([k, j], [pivot, j]);
+            }
+            for j in 0..x.ncols() {
+                x.swap([k, j], [pivot, j]);
+            }
+        }
+        for i in k + 1..n {
+            let factor = a[[i, k]] / a[[k, k]];
+            if factor == 0. {
+                continue;
+            }
+            for j in k..n {
+                a[[i, j]] -= factor * a[[k, j]];
+            }
+            for j in 0..x.ncols() {
+                x[[i, j]] -= factor * x[[k, j]];
+            }
+        }
+    }
+    for k in (0..n).rev() {
+        for j in 0..x.ncols() {
+            let sum: f64 = (k + 1..n).map(|i| a[[k, i]] * x[[i, j]]).sum();
+            x[[k, j]] = (x[[k, j]] - sum) / a[[k, k]];
+        }
+    }
+    x
+}
+
+/// Solves `a x = b`. Panics if `a` is singular.
+pub(crate) fn solve(a: &Matrix, b: &Vector) -> Vector {
+    let b = b.clone().insert_axis(Axis(1));
+    solve_matrix(a, &b).remove_axis(Axis(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn solve_pivots_on_zero_diagonal() {
+        let a = array![[0., 2., 1.], [1., 1., 0.], [3., 0., 1.]];
+        let x = array![1., -2., 3.];
+        let solution = solve(&a, &a.dot(&x));
+        assert!((&solution - &x).mapv(f64::abs).sum() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "singular")]
+    fn solve_rejects_singular_matrices() {
+        solve(&array![[1., 2.], [2., 4.]], &array![1., 1.]);
+    }
+}
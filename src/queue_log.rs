@@ -0,0 +1,168 @@
+/// A customer of a queue with the times it passed through it, identified by the order in which it
+/// entered the queue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Customer {
+    id: u64,
+    class: usize,
+    arrival_time: f64,
+    service_time: f64,
+    /// Service still needed, as of the last time it was interrupted.
+    remaining_service: f64,
+    service_start_time: Option<f64>,
+    departure_time: Option<f64>,
+}
+
+impl Customer {
+    pub(crate) fn new(id: u64, class: usize, arrival_time: f64, service_time: f64) -> Self {
+        Self {
+            id,
+            class,
+            arrival_time,
+            service_time,
+            remaining_service: service_time,
+            service_start_time: None,
+            departure_time: None,
+        }
+    }
+
+    /// Starts or resumes service.
+    pub(crate) fn start_service(&mut self, time: f64) {
+        self.service_start_time.get_or_insert(time);
+    }
+
+    pub(crate) fn remaining_service(&self) -> f64 {
+        self.remaining_service
+    }
+
+    /// Gives the customer `work` units of service.
+    pub(crate) fn serve(&mut self, work: f64) {
+        self.remaining_service = (self.remaining_service - work).max(0.);
+    }
+
+    pub(crate) fn depart(&mut self, time: f64) {
+        debug_assert!(self.departure_time.is_none(), "Customer departed twice.");
+        self.departure_time = Some(time);
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Priority class, 0 being the highest. Always 0 without priority classes.
+    pub fn class(&self) -> usize {
+        self.class
+    }
+
+    pub fn arrival_time(&self) -> f64 {
+        self.arrival_time
+    }
+
+    /// Total service the customer requires.
+    pub fn service_time(&self) -> f64 {
+        self.service_time
+    }
+
+    /// Time service first started, if it has.
+    pub fn service_start_time(&self) -> Option<f64> {
+        self.service_start_time
+    }
+
+    pub fn departure_time(&self) -> Option<f64> {
+        self.departure_time
+    }
+
+    /// Time from arrival until service first started.
+    pub fn waiting_time(&self) -> Option<f64> {
+        self.service_start_time
+            .map(|service_start_time| service_start_time - self.arrival_time)
+    }
+
+    /// Time from arrival until departure.
+    pub fn sojourn_time(&self) -> Option<f64> {
+        self.departure_time
+            .map(|departure_time| departure_time - self.arrival_time)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueEventKind {
+    Arrival,
+    /// Service started or resumed.
+    ServiceStart,
+    /// Service interrupted for a customer of higher priority.
+    Preemption,
+    Departure,
+}
+
+/// Something that happened to a customer at some time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueueEvent {
+    time: f64,
+    customer: u64,
+    kind: QueueEventKind,
+}
+
+impl QueueEvent {
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Id of the customer, see [`Customer::id`].
+    pub fn customer(&self) -> u64 {
+        self.customer
+    }
+
+    pub fn kind(&self) -> QueueEventKind {
+        self.kind
+    }
+}
+
+/// Recorded history of a queue: every event in order and every customer that has departed, in
+/// order of departure.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventLog {
+    events: Vec<QueueEvent>,
+    departures: Vec<Customer>,
+}
+
+impl EventLog {
+    pub(crate) fn push(&mut self, time: f64, customer: u64, kind: QueueEventKind) {
+        debug_assert!(
+            self.events.last().is_none_or(|event| event.time <= time),
+            "Events must be recorded in order."
+        );
+        self.events.push(QueueEvent {
+            time,
+            customer,
+            kind,
+        });
+    }
+
+    pub(crate) fn push_departure(&mut self, customer: Customer) {
+        let time = customer
+            .departure_time
+            .expect("Only departed customers are recorded.");
+        self.push(time, customer.id, QueueEventKind::Departure);
+        self.departures.push(customer);
+    }
+
+    pub fn events(&self) -> &[QueueEvent] {
+        &self.events
+    }
+
+    /// Departed customers with all their times, in order of departure.
+    pub fn departures(&self) -> &[Customer] {
+        &self.departures
+    }
+
+    /// Departed customers that arrived in `[start, end)`, e.g. to leave out a warm-up period.
+    pub fn departures_arrived_in(
+        &self,
+        start: f64,
+        end: f64,
+    ) -> impl Iterator<Item = &Customer> + '_ {
+        self.departures
+            .iter()
+            .filter(move |customer| (start..end).contains(&customer.arrival_time))
+    }
+}
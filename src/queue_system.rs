@@ -1,8 +1,13 @@
-use std::{cmp::Reverse, collections::BinaryHeap};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+};
 
 use ordered_float::OrderedFloat;
 use rand::Rng;
-use rand_distr::{Distribution, Exp};
+use rand_distr::{Distribution, Exp, WeightedIndex};
+
+use crate::{Customer, EventLog, QueueEventKind, StochasticProcess};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 struct RoF64(Reverse<OrderedFloat<f64>>);
@@ -13,36 +18,112 @@ impl From<f64> for RoF64 {
     }
 }
 
-impl Into<f64> for RoF64 {
-    fn into(self) -> f64 {
-        self.0 .0 .0
+impl From<RoF64> for f64 {
+    fn from(value: RoF64) -> Self {
+        value.0 .0 .0
     }
 }
 
-pub trait QueueSystem {
-    fn time(&self) -> f64;
-
+/// A queue observed through its length, whose events are arrivals and departures.
+///
+/// Customers are served by the service units, of which there are infinitely many if the number of
+/// units is `None`, first come, first served unless a [`QueueDiscipline`] says otherwise.
+pub trait QueueSystem: StochasticProcess<State = u64> {
     fn queue_length(&self) -> u64;
 
+    fn num_units(&self) -> Option<u64>;
+
+    /// Moves the queue to its next arrival or departure.
     fn step(&mut self, rng: &mut impl Rng);
 
+    /// Moves the queue `delta_t` forward in time, through all events on the way.
     fn step_t(&mut self, delta_t: f64, rng: &mut impl Rng);
 
+    /// Adds a customer at the current time, on top of the arrival process.
     fn add_arrival(&mut self, rng: &mut impl Rng);
 }
 
+/// Number of busy units with `length` customers in the system.
+fn busy_units(length: u64, num_units: Option<u64>) -> u64 {
+    num_units.map_or(length, |num_units| length.min(num_units))
+}
+
+fn assert_num_units(num_units: Option<u64>) {
+    assert_ne!(
+        num_units,
+        Some(0),
+        "A queue system must contain at least one service unit. Use `None` for infinite."
+    );
+}
+
+/// Order in which a [`GeneralQueueSystem`] serves its customers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum QueueDiscipline {
+    /// First come, first served.
+    #[default]
+    Fifo,
+    /// Last come, first served, without interrupting service.
+    Lifo,
+    /// Lowest class first and first come, first served within a class, without interrupting
+    /// service. An arrival is of class `k` with probability `class_probabilities[k]`.
+    Priority { class_probabilities: Vec<f64> },
+    /// As [`QueueDiscipline::Priority`], but an arrival interrupts the service of the customer of
+    /// the lowest priority if it has a higher one. Service resumes where it stopped.
+    PreemptivePriority { class_probabilities: Vec<f64> },
+    /// Shortest service time first, without interrupting service.
+    ShortestJobFirst,
+    /// All customers are served at once, sharing the units equally.
+    ProcessorSharing,
+}
+
+impl QueueDiscipline {
+    fn class_probabilities(&self) -> Option<&[f64]> {
+        match self {
+            QueueDiscipline::Priority {
+                class_probabilities,
+            }
+            | QueueDiscipline::PreemptivePriority {
+                class_probabilities,
+            } => Some(class_probabilities),
+            _ => None,
+        }
+    }
+}
+
+/// Order of the waiting customers: the largest key is served first.
+type WaitingKey = (Reverse<usize>, Reverse<OrderedFloat<f64>>, Reverse<u64>);
+
+/// A G/G/c queue: interarrival and service times are drawn independently from the given
+/// distributions. Each customer's service time is drawn when it arrives.
+#[derive(Debug, Clone)]
 pub struct GeneralQueueSystem<A, S>
 where
     A: Distribution<f64>,
     S: Distribution<f64>,
 {
-    num_units: u64,
+    num_units: Option<u64>,
     arrival_distribution: A,
     service_distribution: S,
-    length: u64,
+    discipline: QueueDiscipline,
+    class_distribution: Option<WeightedIndex<f64>>,
     time: f64,
     next_arrival_time: f64,
-    queue: BinaryHeap<RoF64>,
+    next_id: u64,
+    /// Customers in the system by id.
+    customers: BTreeMap<u64, Customer>,
+    /// Waiting customers by the order they are served in.
+    waiting: BinaryHeap<(WaitingKey, u64)>,
+    /// Departure times of the customers in service by class and id, so the last one has the
+    /// lowest priority. Unused with processor sharing.
+    in_service: BTreeMap<(usize, u64), f64>,
+    /// Departure times and ids, soonest first. Entries of preempted customers stay in the heap and
+    /// are dropped once they reach the top. With processor sharing, the attained service at
+    /// which each customer departs instead.
+    departures: BinaryHeap<(RoF64, Reverse<u64>)>,
+    /// Service received by a customer present since time 0 under processor sharing, which grows
+    /// at the shared service rate.
+    attained_service: f64,
+    event_log: Option<EventLog>,
 }
 
 impl<A, S> GeneralQueueSystem<A, S>
@@ -50,6 +131,8 @@ where
     A: Distribution<f64>,
     S: Distribution<f64>,
 {
+    /// A first come, first served queue with `start_length` customers at time 0, as many of them
+    /// in service as there are units, and the first arrival after one interarrival time.
     pub fn new(
         num_units: Option<u64>,
         arrival_distribution: A,
@@ -57,66 +140,271 @@ where
         start_length: u64,
         rng: &mut impl Rng,
     ) -> Self {
-        if let Some(num_units) = num_units {
-            assert_ne!(
-                num_units, 0,
-                "A queue system must contain at least one service unit. Use `None` for infinite."
-            );
-        }
+        Self::with_discipline(
+            num_units,
+            arrival_distribution,
+            service_distribution,
+            start_length,
+            QueueDiscipline::Fifo,
+            rng,
+        )
+    }
+
+    /// As [`GeneralQueueSystem::new`], serving customers in the order given by `discipline`.
+    pub fn with_discipline(
+        num_units: Option<u64>,
+        arrival_distribution: A,
+        service_distribution: S,
+        start_length: u64,
+        discipline: QueueDiscipline,
+        rng: &mut impl Rng,
+    ) -> Self {
+        assert_num_units(num_units);
+        let class_distribution = discipline.class_probabilities().map(|class_probabilities| {
+            WeightedIndex::new(class_probabilities).unwrap_or_else(|error| {
+                panic!("Invalid class probabilities {class_probabilities:?}: {error}")
+            })
+        });
         let next_arrival_time = arrival_distribution.sample(rng);
         let mut result = GeneralQueueSystem {
-            num_units: num_units.unwrap_or(0),
+            num_units,
             arrival_distribution,
             service_distribution,
-            length: start_length,
+            discipline,
+            class_distribution,
             time: 0.,
             next_arrival_time,
-            queue: BinaryHeap::new(),
+            next_id: 0,
+            customers: BTreeMap::new(),
+            waiting: BinaryHeap::new(),
+            in_service: BTreeMap::new(),
+            departures: BinaryHeap::new(),
+            attained_service: 0.,
+            event_log: None,
         };
-        result.fill_queue(rng);
+        for _ in 0..start_length {
+            result.add_arrival(rng);
+        }
         result
     }
 
-    fn fill_queue(&mut self, rng: &mut impl Rng) {
-        let max_queue_length = if self.num_units == 0 {
-            self.length
+    /// Records every event from now on in an [`EventLog`], starting with the arrivals and service
+    /// starts of the customers now in the system.
+    pub fn with_event_log(self) -> Self {
+        let mut event_log = EventLog::default();
+        let mut events: Vec<_> = self
+            .customers
+            .values()
+            .flat_map(|customer| {
+                let service_start = customer
+                    .service_start_time()
+                    .map(|time| (time, customer.id(), QueueEventKind::ServiceStart));
+                std::iter::once((
+                    customer.arrival_time(),
+                    customer.id(),
+                    QueueEventKind::Arrival,
+                ))
+                .chain(service_start)
+            })
+            .collect();
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (time, customer, kind) in events {
+            event_log.push(time, customer, kind);
+        }
+        Self {
+            event_log: Some(event_log),
+            ..self
+        }
+    }
+
+    pub fn discipline(&self) -> &QueueDiscipline {
+        &self.discipline
+    }
+
+    /// The recorded history if the queue was created with
+    /// [`GeneralQueueSystem::with_event_log`].
+    pub fn event_log(&self) -> Option<&EventLog> {
+        self.event_log.as_ref()
+    }
+
+    /// The customers in the system, in order of arrival.
+    pub fn customers(&self) -> impl Iterator<Item = &Customer> + '_ {
+        self.customers.values()
+    }
+
+    fn log(&mut self, id: u64, kind: QueueEventKind) {
+        if let Some(event_log) = &mut self.event_log {
+            event_log.push(self.time, id, kind);
+        }
+    }
+
+    fn waiting_key(&self, customer: &Customer) -> WaitingKey {
+        let id = customer.id();
+        match self.discipline {
+            QueueDiscipline::Lifo => (Reverse(0), Reverse(0.into()), Reverse(u64::MAX - id)),
+            QueueDiscipline::ShortestJobFirst => (
+                Reverse(0),
+                Reverse(customer.service_time().into()),
+                Reverse(id),
+            ),
+            _ => (Reverse(customer.class()), Reverse(0.into()), Reverse(id)),
+        }
+    }
+
+    fn start_service(&mut self, id: u64) {
+        let customer = self.customers.get_mut(&id).unwrap();
+        customer.start_service(self.time);
+        let departure_time = self.time + customer.remaining_service();
+        self.in_service
+            .insert((customer.class(), id), departure_time);
+        self.departures.push((departure_time.into(), Reverse(id)));
+        self.log(id, QueueEventKind::ServiceStart);
+    }
+
+    /// Interrupts the service of `id` and puts it back in the waiting line. Its entry in
+    /// `departures` goes stale.
+    fn preempt(&mut self, class: usize, id: u64) {
+        let departure_time = self.in_service.remove(&(class, id)).unwrap();
+        let customer = self.customers.get_mut(&id).unwrap();
+        customer.serve(customer.remaining_service() - (departure_time - self.time));
+        let key = self.waiting_key(&self.customers[&id]);
+        self.waiting.push((key, id));
+        self.log(id, QueueEventKind::Preemption);
+    }
+
+    /// Drops the entries of preempted customers from the top of `departures`, so its top is the
+    /// next departure.
+    fn discard_stale_departures(&mut self) {
+        while let Some(&(departure_time, Reverse(id))) = self.departures.peek() {
+            let is_current = self.customers.get(&id).is_some_and(|customer| {
+                self.in_service.get(&(customer.class(), id)) == Some(&departure_time.into())
+            });
+            if is_current {
+                return;
+            }
+            self.departures.pop();
+        }
+    }
+
+    /// Starts service for waiting customers while there are free units, and with preemption
+    /// while a waiting customer has a higher priority than one in service.
+    fn fill_queue(&mut self) {
+        while self
+            .num_units
+            .is_none_or(|num_units| (self.in_service.len() as u64) < num_units)
+        {
+            let Some((_, id)) = self.waiting.pop() else {
+                break;
+            };
+            self.start_service(id);
+        }
+        if matches!(self.discipline, QueueDiscipline::PreemptivePriority { .. }) {
+            while let Some(&(_, id)) = self.waiting.peek() {
+                let (&(lowest_class, lowest_id), _) = self.in_service.last_key_value().unwrap();
+                if lowest_class <= self.customers[&id].class() {
+                    break;
+                }
+                self.preempt(lowest_class, lowest_id);
+                self.waiting.pop();
+                self.start_service(id);
+            }
+        }
+        self.discard_stale_departures();
+    }
+
+    /// Rate at which each customer is served under processor sharing.
+    fn shared_service_rate(&self) -> f64 {
+        let length = self.queue_length();
+        busy_units(length, self.num_units) as f64 / length as f64
+    }
+
+    /// Time and id of the next departure, if anyone is in service.
+    fn next_departure(&self) -> Option<(f64, u64)> {
+        let &(departure, Reverse(id)) = self.departures.peek()?;
+        if self.discipline == QueueDiscipline::ProcessorSharing {
+            let remaining_service = (f64::from(departure) - self.attained_service).max(0.);
+            Some((
+                self.time + remaining_service / self.shared_service_rate(),
+                id,
+            ))
         } else {
-            self.length.min(self.num_units)
-        } as usize;
-        while self.queue.len() < max_queue_length {
-            self.queue
-                .push((self.time + self.service_distribution.sample(rng)).into())
+            Some((departure.into(), id))
+        }
+    }
+
+    fn next_event_time(&self) -> f64 {
+        self.next_departure()
+            .map_or(f64::INFINITY, |(departure_time, _)| departure_time)
+            .min(self.next_arrival_time)
+    }
+
+    /// Moves the time forward to `time`, before any event, serving the customers on the way.
+    fn serve_until(&mut self, time: f64) {
+        if self.discipline == QueueDiscipline::ProcessorSharing && !self.customers.is_empty() {
+            self.attained_service += (time - self.time) * self.shared_service_rate();
         }
+        self.time = time;
     }
 }
 
-impl<A, S> QueueSystem for GeneralQueueSystem<A, S>
+impl<A, S> StochasticProcess for GeneralQueueSystem<A, S>
 where
     A: Distribution<f64>,
     S: Distribution<f64>,
 {
+    type State = u64;
+
     fn time(&self) -> f64 {
         self.time
     }
 
+    fn state(&self) -> u64 {
+        self.queue_length()
+    }
+
+    fn next_event(&mut self, rng: &mut impl Rng) -> bool {
+        self.step(rng);
+        true
+    }
+
+    fn advance_to(&mut self, time: f64, rng: &mut impl Rng) {
+        self.step_t(time - self.time, rng);
+    }
+}
+
+impl<A, S> QueueSystem for GeneralQueueSystem<A, S>
+where
+    A: Distribution<f64>,
+    S: Distribution<f64>,
+{
     fn queue_length(&self) -> u64 {
-        self.length
+        self.customers.len() as u64
+    }
+
+    fn num_units(&self) -> Option<u64> {
+        self.num_units
     }
 
     fn step(&mut self, rng: &mut impl Rng) {
-        if let Some(&next_service_time) = self
-            .queue
-            .peek()
-            .filter(|&&next_service_time| self.next_arrival_time > next_service_time.into())
+        if let Some((departure_time, id)) = self
+            .next_departure()
+            .filter(|&(departure_time, _)| self.next_arrival_time > departure_time)
         {
-            self.queue.pop();
-            self.time = next_service_time.into();
-            self.length -= 1;
+            self.serve_until(departure_time);
+            self.departures.pop();
+            let mut customer = self.customers.remove(&id).unwrap();
+            customer.depart(self.time);
+            if let Some(event_log) = &mut self.event_log {
+                event_log.push_departure(customer);
+            }
+            if self.discipline != QueueDiscipline::ProcessorSharing {
+                self.in_service.remove(&(customer.class(), id));
+                self.fill_queue();
+            }
         } else {
-            self.time = self.next_arrival_time;
+            self.serve_until(self.next_arrival_time);
             self.next_arrival_time = self.time + self.arrival_distribution.sample(rng);
-            self.length += 1;
-            self.fill_queue(rng);
+            self.add_arrival(rng);
         }
     }
 
@@ -127,33 +415,47 @@ where
             self.time,
             delta_t
         );
-        self.time += delta_t;
-        while self.time > self.next_arrival_time {
-            self.length += 1;
-            self.fill_queue(rng);
-            self.next_arrival_time += self.arrival_distribution.sample(rng);
-        }
-        while let Some(_) = self
-            .queue
-            .peek()
-            .filter(|&&next_service_time| self.time > next_service_time.into())
-        {
-            self.length -= 1;
-            self.queue.pop();
+        let end_time = self.time + delta_t;
+        while self.next_event_time() <= end_time {
+            self.step(rng);
         }
+        self.serve_until(end_time);
     }
 
     fn add_arrival(&mut self, rng: &mut impl Rng) {
-        self.length += 1;
-        self.fill_queue(rng)
+        let id = self.next_id;
+        self.next_id += 1;
+        let class = self
+            .class_distribution
+            .as_ref()
+            .map_or(0, |class_distribution| class_distribution.sample(rng));
+        let service_time = self.service_distribution.sample(rng);
+        let customer = Customer::new(id, class, self.time, service_time);
+        self.customers.insert(id, customer);
+        self.log(id, QueueEventKind::Arrival);
+        if self.discipline == QueueDiscipline::ProcessorSharing {
+            self.customers
+                .get_mut(&id)
+                .unwrap()
+                .start_service(self.time);
+            let departure = self.attained_service + service_time;
+            self.departures.push((departure.into(), Reverse(id)));
+            self.log(id, QueueEventKind::ServiceStart);
+        } else {
+            self.waiting.push((self.waiting_key(&customer), id));
+            self.fill_queue();
+        }
     }
 }
 
-struct MarkovServiceQueueSystem<A>
+/// A G/M/c queue: interarrival times are drawn from the given distribution and each unit serves
+/// at `service_rate`. Only the time of the next departure is kept.
+#[derive(Debug, Clone)]
+pub struct MarkovServiceQueueSystem<A>
 where
     A: Distribution<f64>,
 {
-    num_units: u64,
+    num_units: Option<u64>,
     arrival_distribution: A,
     service_rate: f64,
     length: u64,
@@ -166,6 +468,8 @@ impl<A> MarkovServiceQueueSystem<A>
 where
     A: Distribution<f64>,
 {
+    /// A queue with `start_length` customers at time 0 and the first arrival after one
+    /// interarrival time.
     pub fn new(
         num_units: Option<u64>,
         arrival_distribution: A,
@@ -173,56 +477,81 @@ where
         start_length: u64,
         rng: &mut impl Rng,
     ) -> Self {
-        if let Some(num_units) = num_units {
-            assert_ne!(
-                num_units, 0,
-                "A queue system must contain at least one service unit. Use `None` for infinite."
-            );
-        }
+        assert_num_units(num_units);
+        assert!(
+            service_rate > 0.,
+            "Service rate must be positive. Got {service_rate}"
+        );
         let next_arrival_time = arrival_distribution.sample(rng);
-        let next_service_time = if start_length > 0 {
-            Exp::new((start_length as f64) * service_rate)
-                .unwrap()
-                .sample(rng)
-        } else {
-            f64::INFINITY
-        };
-        MarkovServiceQueueSystem {
-            num_units: num_units.unwrap_or(0),
+        let mut result = MarkovServiceQueueSystem {
+            num_units,
             arrival_distribution,
             service_rate,
             length: start_length,
             time: 0.,
             next_arrival_time,
-            next_service_time,
-        }
+            next_service_time: f64::INFINITY,
+        };
+        result.draw_service_time(rng);
+        result
+    }
+
+    pub fn service_rate(&self) -> f64 {
+        self.service_rate
+    }
+
+    /// Draws the next departure from now, at the rate of the busy units.
+    fn draw_service_time(&mut self, rng: &mut impl Rng) {
+        self.next_service_time = if self.length > 0 {
+            let rate = self.service_rate * busy_units(self.length, self.num_units) as f64;
+            self.time + Exp::new(rate).unwrap().sample(rng)
+        } else {
+            f64::INFINITY
+        };
     }
 }
 
-impl<A> QueueSystem for MarkovServiceQueueSystem<A>
+impl<A> StochasticProcess for MarkovServiceQueueSystem<A>
 where
     A: Distribution<f64>,
 {
+    type State = u64;
+
     fn time(&self) -> f64 {
         self.time
     }
 
+    fn state(&self) -> u64 {
+        self.length
+    }
+
+    fn next_event(&mut self, rng: &mut impl Rng) -> bool {
+        self.step(rng);
+        true
+    }
+
+    fn advance_to(&mut self, time: f64, rng: &mut impl Rng) {
+        self.step_t(time - self.time, rng);
+    }
+}
+
+impl<A> QueueSystem for MarkovServiceQueueSystem<A>
+where
+    A: Distribution<f64>,
+{
     fn queue_length(&self) -> u64 {
         self.length
     }
 
+    fn num_units(&self) -> Option<u64> {
+        self.num_units
+    }
+
     fn step(&mut self, rng: &mut impl Rng) {
         if self.next_service_time < self.next_arrival_time {
             self.time = self.next_service_time;
             self.length -= 1;
-            if self.length > 0 {
-                self.next_service_time +=
-                    Exp::new(self.service_rate * self.length.min(self.num_units) as f64)
-                        .unwrap()
-                        .sample(rng);
-            } else {
-                self.next_service_time = f64::INFINITY;
-            }
+            self.draw_service_time(rng);
         } else {
             self.time = self.next_arrival_time;
             self.next_arrival_time += self.arrival_distribution.sample(rng);
@@ -237,39 +566,16 @@ where
             self.time,
             delta_t
         );
-        self.time += delta_t;
-        while self.time > self.next_arrival_time.min(self.next_service_time) {
-            if self.next_service_time < self.next_arrival_time {
-                self.length -= 1;
-                if self.length > 0 {
-                    self.next_service_time +=
-                        Exp::new(self.service_rate * self.length.min(self.num_units) as f64)
-                            .unwrap()
-                            .sample(rng);
-                } else {
-                    self.next_service_time = f64::INFINITY;
-                }
-            } else {
-                self.next_arrival_time += self.arrival_distribution.sample(rng);
-                self.add_arrival(rng);
-            }
+        let end_time = self.time + delta_t;
+        while self.next_arrival_time.min(self.next_service_time) <= end_time {
+            self.step(rng);
         }
+        self.time = end_time;
     }
 
     fn add_arrival(&mut self, rng: &mut impl Rng) {
-        if self.length == 0 {
-            assert_eq!(self.next_service_time, f64::INFINITY);
-            self.next_service_time = Exp::new(self.service_rate).unwrap().sample(rng);
-            self.length += 1;
-        } else {
-            assert_ne!(self.next_service_time, f64::INFINITY);
-            assert!(self.next_service_time > 0.);
-            if self.length < self.num_units {
-                self.next_service_time = self.time
-                    + (self.next_service_time - self.time) * (self.length + 1) as f64
-                        / self.length as f64;
-            }
-            self.length += 1;
-        }
+        self.length += 1;
+        // Service is memoryless, so the next departure can be redrawn at the new rate.
+        self.draw_service_time(rng);
     }
 }
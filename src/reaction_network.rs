@@ -0,0 +1,267 @@
+use std::{fmt, sync::Arc};
+
+use rand::Rng;
+use rand_distr::{Distribution, Exp, Exp1};
+
+use crate::ContinuousMarkovTransitions;
+
+/// Rate of a reaction as a function of the populations.
+type Propensity<const N: usize> = dyn Fn(&[u64; N]) -> f64 + Send + Sync;
+
+/// A reaction on `N` species, firing at the rate given by its propensity in the current state and
+/// changing the populations by its change vector.
+#[derive(Clone)]
+pub struct Reaction<const N: usize> {
+    propensity: Arc<Propensity<N>>,
+    change: [i64; N],
+}
+
+impl<const N: usize> Reaction<N> {
+    pub fn new(
+        propensity: impl Fn(&[u64; N]) -> f64 + Send + Sync + 'static,
+        change: [i64; N],
+    ) -> Self {
+        Self {
+            propensity: Arc::new(propensity),
+            change,
+        }
+    }
+
+    /// A reaction with mass-action kinetics, firing at `rate` times the number of ways to pick
+    /// `reactants[i]` individuals of each species `i`.
+    pub fn mass_action(rate: f64, reactants: [u64; N], change: [i64; N]) -> Self {
+        assert!(rate >= 0., "Rate must be non-negative. Got {rate}");
+        let propensity = move |state: &[u64; N]| {
+            state
+                .iter()
+                .zip(&reactants)
+                .map(|(&population, &needed)| {
+                    (0..needed)
+                        .map(|j| population.saturating_sub(j) as f64 / (j + 1) as f64)
+                        .product::<f64>()
+                })
+                .product::<f64>()
+                * rate
+        };
+        Self::new(propensity, change)
+    }
+
+    /// Rate at which the reaction fires in `state`.
+    pub fn propensity(&self, state: &[u64; N]) -> f64 {
+        let propensity = (self.propensity)(state);
+        assert!(
+            propensity >= 0. && propensity.is_finite(),
+            "Propensities must be non-negative. Got {propensity} in {state:?}"
+        );
+        propensity
+    }
+
+    pub fn change(&self) -> &[i64; N] {
+        &self.change
+    }
+
+    /// The state after the reaction fires in `state`.
+    fn apply(&self, state: &[u64; N]) -> [u64; N] {
+        let mut next_state = *state;
+        for (i, (population, &change)) in next_state.iter_mut().zip(&self.change).enumerate() {
+            *population = population.checked_add_signed(change).unwrap_or_else(|| {
+                panic!(
+                    "Reaction {:?} would make species {i} negative in {state:?}",
+                    self.change
+                )
+            });
+        }
+        next_state
+    }
+}
+
+impl<const N: usize> fmt::Debug for Reaction<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reaction")
+            .field("change", &self.change)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A CTMC on the populations of `N` species, given by the reactions that can change them, e.g. a
+/// queueing network with one species per queue. As transitions it is simulated by Gillespie's
+/// direct method.
+#[derive(Debug, Clone)]
+pub struct ReactionNetwork<const N: usize> {
+    reactions: Vec<Reaction<N>>,
+}
+
+impl<const N: usize> ReactionNetwork<N> {
+    pub fn new(reactions: Vec<Reaction<N>>) -> Self {
+        Self { reactions }
+    }
+
+    pub fn reactions(&self) -> &[Reaction<N>] {
+        &self.reactions
+    }
+
+    /// The propensity of each reaction in `state`.
+    pub fn propensities(&self, state: &[u64; N]) -> Vec<f64> {
+        self.reactions
+            .iter()
+            .map(|reaction| reaction.propensity(state))
+            .collect()
+    }
+
+    /// The network simulated by the next-reaction method instead.
+    pub fn next_reaction_method(self) -> NextReactionMethod<N> {
+        NextReactionMethod {
+            network: self,
+            clocks: Vec::new(),
+        }
+    }
+}
+
+impl<const N: usize> ContinuousMarkovTransitions for ReactionNetwork<N> {
+    type State = [u64; N];
+
+    fn next_transition(
+        &mut self,
+        from_state: &[u64; N],
+        _time: f64,
+        rng: &mut impl Rng,
+    ) -> Option<([u64; N], f64)> {
+        let propensities = self.propensities(from_state);
+        let total_propensity: f64 = propensities.iter().sum();
+        if total_propensity == 0. {
+            // No reaction can fire again.
+            return None;
+        }
+        let time_to_next_transition = Exp::new(total_propensity).unwrap().sample(rng);
+
+        let rng_value = rng.gen_range(0. ..total_propensity);
+        let mut cumulative_propensity = 0.;
+        let index = propensities
+            .iter()
+            .position(|&propensity| {
+                cumulative_propensity += propensity;
+                cumulative_propensity > rng_value
+            })
+            // Rounding can leave the cumulative propensity just below the total.
+            .unwrap_or_else(|| propensities.iter().rposition(|&p| p > 0.).unwrap());
+        let next_state = self.reactions[index].apply(from_state);
+        Some((next_state, time_to_next_transition))
+    }
+}
+
+/// A [`ReactionNetwork`] simulated by the modified next-reaction method (Anderson, 2007): each
+/// reaction fires at the jumps of its own unit-rate Poisson process run at the integrated
+/// propensity, so only one exponential is drawn per step.
+///
+/// The internal clocks belong to the path being simulated, so each process needs its own copy.
+#[derive(Debug, Clone)]
+pub struct NextReactionMethod<const N: usize> {
+    network: ReactionNetwork<N>,
+    /// Internal time and internal time of the next firing of each reaction, empty until the first
+    /// step.
+    clocks: Vec<(f64, f64)>,
+}
+
+impl<const N: usize> NextReactionMethod<N> {
+    pub fn network(&self) -> &ReactionNetwork<N> {
+        &self.network
+    }
+}
+
+impl<const N: usize> ContinuousMarkovTransitions for NextReactionMethod<N> {
+    type State = [u64; N];
+
+    fn next_transition(
+        &mut self,
+        from_state: &[u64; N],
+        _time: f64,
+        rng: &mut impl Rng,
+    ) -> Option<([u64; N], f64)> {
+        if self.clocks.is_empty() {
+            self.clocks = (0..self.network.reactions.len())
+                .map(|_| (0., Exp1.sample(rng)))
+                .collect();
+        }
+        let propensities = self.network.propensities(from_state);
+        let (index, time_to_next_transition) = propensities
+            .iter()
+            .zip(&self.clocks)
+            .enumerate()
+            .filter(|&(_, (&propensity, _))| propensity > 0.)
+            .map(|(k, (&propensity, &(internal_time, next_firing)))| {
+                (k, (next_firing - internal_time) / propensity)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+        for ((internal_time, _), &propensity) in self.clocks.iter_mut().zip(&propensities) {
+            *internal_time += propensity * time_to_next_transition;
+        }
+        // The firing reaction is exactly at its next firing, which rounding may not reproduce.
+        let gap: f64 = Exp1.sample(rng);
+        let (internal_time, next_firing) = &mut self.clocks[index];
+        *internal_time = *next_firing;
+        *next_firing += gap;
+
+        let next_state = self.network.reactions[index].apply(from_state);
+        Some((next_state, time_to_next_transition))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_pcg::Pcg64Mcg;
+
+    use super::*;
+    use crate::{ContinuousMarkovProcess, StochasticProcess};
+
+    #[test]
+    fn mass_action_counts_combinations() {
+        let dimerization = Reaction::mass_action(0.5, [2], [-2]);
+        for (population, combinations) in [(0, 0.), (1, 0.), (2, 1.), (5, 10.)] {
+            assert_eq!(dimerization.propensity(&[population]), 0.5 * combinations);
+        }
+        let binding = Reaction::mass_action(2., [2, 1, 0], [-2, -1, 1]);
+        assert_eq!(binding.propensity(&[4, 3, 7]), 2. * 6. * 3.);
+        assert_eq!(binding.propensity(&[4, 0, 7]), 0.);
+        let immigration = Reaction::mass_action(1.5, [0], [1]);
+        assert_eq!(immigration.propensity(&[9]), 1.5);
+    }
+
+    /// Immigration at `λ` and death at `μ` per individual, whose stationary law is Poisson with
+    /// mean `λ / μ`.
+    fn immigration_death(immigration_rate: f64, death_rate: f64) -> ReactionNetwork<1> {
+        ReactionNetwork::new(vec![
+            Reaction::mass_action(immigration_rate, [0], [1]),
+            Reaction::mass_action(death_rate, [1], [-1]),
+        ])
+    }
+
+    fn assert_poisson<M>(transitions: M, mean: f64, seed: u128)
+    where
+        M: ContinuousMarkovTransitions<State = [u64; 1]>,
+    {
+        let mut rng = Pcg64Mcg::new(seed);
+        let mut process = ContinuousMarkovProcess::new(transitions, [0]);
+        let occupation = process.occupation(100., 50_100., &mut rng);
+        let empirical_mean: f64 = occupation
+            .iter()
+            .map(|(&[population], probability)| population as f64 * probability)
+            .sum();
+        assert!(
+            (empirical_mean - mean).abs() < 0.06,
+            "{empirical_mean} vs {mean}"
+        );
+        let empty = occupation.get(&[0]).copied().unwrap_or(0.);
+        assert!((empty - (-mean).exp()).abs() < 0.005, "{empty}");
+    }
+
+    #[test]
+    fn direct_method_matches_poisson_stationary_law() {
+        assert_poisson(immigration_death(5., 1.), 5., 1);
+    }
+
+    #[test]
+    fn next_reaction_method_matches_poisson_stationary_law() {
+        assert_poisson(immigration_death(5., 1.).next_reaction_method(), 5., 2);
+    }
+}
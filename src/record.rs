@@ -0,0 +1,258 @@
+use std::{
+    fmt::{self, Display},
+    ops::Index,
+};
+
+use crate::{
+    sample::{merge_component, push_component, variance_component},
+    Accumulator, Moments, Sample, WelfordAccumulator,
+};
+
+/// A sample made up of several named scalar statistics, e.g. the queue length and the waiting
+/// time of the same simulated path.
+///
+/// The theory for a record-valued experiment must return a record with the same names in the
+/// same order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Record<const N: usize> {
+    names: [&'static str; N],
+    values: [f64; N],
+}
+
+impl<const N: usize> Record<N> {
+    pub fn new(statistics: [(&'static str, f64); N]) -> Self {
+        Self {
+            names: statistics.map(|(name, _)| name),
+            values: statistics.map(|(_, value)| value),
+        }
+    }
+
+    pub fn names(&self) -> &[&'static str; N] {
+        &self.names
+    }
+
+    pub fn values(&self) -> &[f64; N] {
+        &self.values
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.position(name).map(|index| self.values[index])
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|&other| other == name)
+    }
+
+    fn assert_same_names(&self, other: &Self) {
+        assert_eq!(
+            self.names, other.names,
+            "Records must have the same statistics in the same order."
+        );
+    }
+}
+
+impl<const N: usize> Index<&str> for Record<N> {
+    type Output = f64;
+
+    fn index(&self, name: &str) -> &f64 {
+        let index = self
+            .position(name)
+            .unwrap_or_else(|| panic!("No statistic named {name}."));
+        &self.values[index]
+    }
+}
+
+impl<const N: usize> Display for Record<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (name, value)) in self.names.iter().zip(&self.values).enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{name}: {value}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Sample for Record<N> {
+    type Accumulator = WelfordAccumulator<Record<N>>;
+
+    fn map_components(&self, f: impl Fn(f64) -> f64) -> Self {
+        Self {
+            names: self.names,
+            values: self.values.map(f),
+        }
+    }
+
+    fn zip_components(&self, other: &Self, f: impl Fn(f64, f64) -> f64) -> Self {
+        self.assert_same_names(other);
+        let mut values = self.values;
+        for (value, &other) in values.iter_mut().zip(&other.values) {
+            *value = f(*value, other);
+        }
+        Self {
+            names: self.names,
+            values,
+        }
+    }
+
+    fn components(&self) -> Vec<f64> {
+        self.values.to_vec()
+    }
+
+    fn component_names(&self) -> Vec<String> {
+        self.names.iter().map(|name| name.to_string()).collect()
+    }
+}
+
+impl<const N: usize> Accumulator<Record<N>> for WelfordAccumulator<Record<N>> {
+    /// The names are taken from the first sample pushed or merged.
+    fn zero() -> Self {
+        let zero = Record {
+            names: [""; N],
+            values: [0.; N],
+        };
+        Self {
+            count: 0,
+            mean: zero,
+            squared_deviations: zero,
+        }
+    }
+
+    fn push(&mut self, sample: &Record<N>) {
+        if self.count == 0 {
+            self.mean.names = sample.names;
+            self.squared_deviations.names = sample.names;
+        }
+        self.mean.assert_same_names(sample);
+        self.count += 1;
+        for ((mean, squared_deviations), &sample) in self
+            .mean
+            .values
+            .iter_mut()
+            .zip(&mut self.squared_deviations.values)
+            .zip(&sample.values)
+        {
+            push_component(self.count, mean, squared_deviations, sample);
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = other;
+            return;
+        }
+        self.mean.assert_same_names(&other.mean);
+        let counts = (self.count, other.count);
+        for (((mean, squared_deviations), &other_mean), &other_squared_deviations) in self
+            .mean
+            .values
+            .iter_mut()
+            .zip(&mut self.squared_deviations.values)
+            .zip(&other.mean.values)
+            .zip(&other.squared_deviations.values)
+        {
+            merge_component(
+                counts,
+                mean,
+                squared_deviations,
+                other_mean,
+                other_squared_deviations,
+            );
+        }
+        self.count += other.count;
+    }
+
+    fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn finalize(&self) -> Moments<Record<N>> {
+        let count = self.count;
+        Moments::new(
+            count,
+            self.mean,
+            self.squared_deviations
+                .map_components(|squared_deviations| variance_component(count, squared_deviations)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{accumulate, assert_close};
+
+    const SAMPLES: [f64; 5] = [3.5, -1.25, 8., 0., 1e3];
+
+    fn record(length: f64, wait: f64) -> Record<2> {
+        Record::new([("length", length), ("wait", wait)])
+    }
+
+    #[test]
+    fn components_keep_their_names() {
+        let sample = record(2., -3.);
+        assert_eq!(sample.component_names(), ["length", "wait"]);
+        assert_eq!(sample.components(), [2., -3.]);
+        assert_eq!(sample["wait"], -3.);
+        assert_eq!(sample.get("length"), Some(2.));
+        assert_eq!(sample.get("queue"), None);
+
+        let doubled = sample.map_components(|value| 2. * value);
+        assert_eq!(doubled, record(4., -6.));
+        let sum = sample.zip_components(&record(1., 10.), |a, b| a + b);
+        assert_eq!(sum, record(3., 7.));
+        assert_eq!(sum.names(), sample.names());
+    }
+
+    #[test]
+    #[should_panic(expected = "same statistics in the same order")]
+    fn zip_with_other_names_panics() {
+        let swapped = Record::new([("wait", 1.), ("length", 2.)]);
+        record(1., 2.).zip_components(&swapped, |a, b| a + b);
+    }
+
+    #[test]
+    #[should_panic(expected = "No statistic named queue")]
+    fn index_by_unknown_name_panics() {
+        let _ = record(1., 2.)["queue"];
+    }
+
+    #[test]
+    fn display_lists_named_values() {
+        assert_eq!(record(1.5, -2.).to_string(), "length: 1.5, wait: -2");
+    }
+
+    #[test]
+    fn merge_matches_single_pass_per_component() {
+        let samples: Vec<_> = SAMPLES.iter().map(|&x| record(x, x * x)).collect();
+        let single_pass = accumulate(samples.iter().copied()).finalize();
+        assert_eq!(single_pass.mean().names(), &["length", "wait"]);
+        for split in 0..=samples.len() {
+            let mut accumulator = accumulate(samples[..split].iter().copied());
+            accumulator.merge(accumulate(samples[split..].iter().copied()));
+            let moments = accumulator.finalize();
+            assert_eq!(moments.count(), single_pass.count());
+            assert_eq!(moments.mean().names(), single_pass.mean().names());
+            for (actual, expected) in moments
+                .mean()
+                .values()
+                .iter()
+                .zip(single_pass.mean().values())
+            {
+                assert_close(*actual, *expected);
+            }
+            for (actual, expected) in moments
+                .variance()
+                .values()
+                .iter()
+                .zip(single_pass.variance().values())
+            {
+                assert_close(*actual, *expected);
+            }
+        }
+    }
+}
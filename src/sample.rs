@@ -0,0 +1,353 @@
+use std::fmt::Display;
+
+use ndarray::{Array, Dimension, Zip};
+
+/// A value produced by an experiment that can be averaged over many runs.
+pub trait Sample: Sized + Clone + Send + Sync + Display {
+    type Accumulator: Accumulator<Self>;
+
+    /// Applies `f` to every component, e.g. to turn variances into standard errors.
+    fn map_components(&self, f: impl Fn(f64) -> f64) -> Self;
+
+    /// Combines corresponding components of two samples of the same shape.
+    fn zip_components(&self, other: &Self, f: impl Fn(f64, f64) -> f64) -> Self;
+
+    /// All components in a fixed order.
+    fn components(&self) -> Vec<f64>;
+
+    /// Names of the components in the order of [`Sample::components`], used in reports.
+    fn component_names(&self) -> Vec<String>;
+
+    fn mean<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        let mut accumulator = Self::Accumulator::zero();
+        for sample in iter {
+            accumulator.push(&sample);
+        }
+        accumulator.finalize().mean
+    }
+}
+
+/// Running statistics of a stream of samples, updated in place so memory use does not grow with
+/// the number of samples.
+pub trait Accumulator<S>: Clone + Send {
+    /// Accumulator of no samples.
+    fn zero() -> Self;
+
+    fn push(&mut self, sample: &S);
+
+    /// Adds the samples accumulated in `other`, which must be disjoint from those in `self`.
+    fn merge(&mut self, other: Self);
+
+    fn count(&self) -> u64;
+
+    fn finalize(&self) -> Moments<S>;
+}
+
+/// Sample count, mean and unbiased variance of a set of samples.
+#[derive(Debug, Clone, Copy)]
+pub struct Moments<S> {
+    count: u64,
+    mean: S,
+    variance: S,
+}
+
+impl<S> Moments<S> {
+    pub(crate) fn new(count: u64, mean: S, variance: S) -> Self {
+        Self {
+            count,
+            mean,
+            variance,
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> &S {
+        &self.mean
+    }
+
+    pub fn variance(&self) -> &S {
+        &self.variance
+    }
+
+    pub(crate) fn into_parts(self) -> (u64, S, S) {
+        (self.count, self.mean, self.variance)
+    }
+}
+
+/// Running mean and sum of squared deviations, updated with Welford's algorithm.
+///
+/// For array samples the statistics are kept per component.
+#[derive(Debug, Clone)]
+pub struct WelfordAccumulator<S> {
+    pub(crate) count: u64,
+    pub(crate) mean: S,
+    pub(crate) squared_deviations: S,
+}
+
+pub(crate) fn push_component(
+    count: u64,
+    mean: &mut f64,
+    squared_deviations: &mut f64,
+    sample: f64,
+) {
+    let delta = sample - *mean;
+    *mean += delta / count as f64;
+    *squared_deviations += delta * (sample - *mean);
+}
+
+/// Combines the statistics of two disjoint sets of samples (Chan et al.).
+pub(crate) fn merge_component(
+    (count, other_count): (u64, u64),
+    mean: &mut f64,
+    squared_deviations: &mut f64,
+    other_mean: f64,
+    other_squared_deviations: f64,
+) {
+    let total_count = (count + other_count) as f64;
+    let delta = other_mean - *mean;
+    *mean += delta * other_count as f64 / total_count;
+    *squared_deviations +=
+        other_squared_deviations + delta * delta * count as f64 * other_count as f64 / total_count;
+}
+
+pub(crate) fn variance_component(count: u64, squared_deviations: f64) -> f64 {
+    squared_deviations / (count as f64 - 1.)
+}
+
+impl Sample for f64 {
+    type Accumulator = WelfordAccumulator<f64>;
+
+    fn map_components(&self, f: impl Fn(f64) -> f64) -> Self {
+        f(*self)
+    }
+
+    fn zip_components(&self, other: &Self, f: impl Fn(f64, f64) -> f64) -> Self {
+        f(*self, *other)
+    }
+
+    fn components(&self) -> Vec<f64> {
+        vec![*self]
+    }
+
+    fn component_names(&self) -> Vec<String> {
+        vec!["value".to_string()]
+    }
+}
+
+impl Accumulator<f64> for WelfordAccumulator<f64> {
+    fn zero() -> Self {
+        Self {
+            count: 0,
+            mean: 0.,
+            squared_deviations: 0.,
+        }
+    }
+
+    fn push(&mut self, &sample: &f64) {
+        self.count += 1;
+        push_component(
+            self.count,
+            &mut self.mean,
+            &mut self.squared_deviations,
+            sample,
+        );
+    }
+
+    fn merge(&mut self, other: Self) {
+        if other.count == 0 {
+            return;
+        }
+        merge_component(
+            (self.count, other.count),
+            &mut self.mean,
+            &mut self.squared_deviations,
+            other.mean,
+            other.squared_deviations,
+        );
+        self.count += other.count;
+    }
+
+    fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn finalize(&self) -> Moments<f64> {
+        Moments {
+            count: self.count,
+            mean: self.mean,
+            variance: variance_component(self.count, self.squared_deviations),
+        }
+    }
+}
+
+impl<D> Sample for Array<f64, D>
+where
+    D: Dimension,
+{
+    type Accumulator = WelfordAccumulator<Array<f64, D>>;
+
+    fn map_components(&self, f: impl Fn(f64) -> f64) -> Self {
+        self.mapv(f)
+    }
+
+    fn zip_components(&self, other: &Self, f: impl Fn(f64, f64) -> f64) -> Self {
+        assert_eq!(
+            self.shape(),
+            other.shape(),
+            "Samples must have the same shape."
+        );
+        Zip::from(self).and(other).map_collect(|&a, &b| f(a, b))
+    }
+
+    /// Components in logical row-major order.
+    fn components(&self) -> Vec<f64> {
+        self.iter().copied().collect()
+    }
+
+    /// Indices of the components, e.g. `3` for a vector or `(1, 2)` for a matrix.
+    fn component_names(&self) -> Vec<String> {
+        self.indexed_iter()
+            .map(|(index, _)| format!("{index:?}"))
+            .collect()
+    }
+}
+
+impl<D> Accumulator<Array<f64, D>> for WelfordAccumulator<Array<f64, D>>
+where
+    D: Dimension,
+{
+    /// The shape is taken from the first sample pushed or merged.
+    fn zero() -> Self {
+        Self {
+            count: 0,
+            mean: Array::zeros(D::default()),
+            squared_deviations: Array::zeros(D::default()),
+        }
+    }
+
+    fn push(&mut self, sample: &Array<f64, D>) {
+        if self.count == 0 {
+            self.mean = Array::zeros(sample.raw_dim());
+            self.squared_deviations = Array::zeros(sample.raw_dim());
+        }
+        assert_eq!(
+            self.mean.shape(),
+            sample.shape(),
+            "All samples must have the same shape."
+        );
+        self.count += 1;
+        let count = self.count;
+        Zip::from(&mut self.mean)
+            .and(&mut self.squared_deviations)
+            .and(sample)
+            .for_each(|mean, squared_deviations, &sample| {
+                push_component(count, mean, squared_deviations, sample)
+            });
+    }
+
+    fn merge(&mut self, other: Self) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = other;
+            return;
+        }
+        assert_eq!(
+            self.mean.shape(),
+            other.mean.shape(),
+            "All samples must have the same shape."
+        );
+        let counts = (self.count, other.count);
+        Zip::from(&mut self.mean)
+            .and(&mut self.squared_deviations)
+            .and(&other.mean)
+            .and(&other.squared_deviations)
+            .for_each(
+                |mean, squared_deviations, &other_mean, &other_squared_deviations| {
+                    merge_component(
+                        counts,
+                        mean,
+                        squared_deviations,
+                        other_mean,
+                        other_squared_deviations,
+                    )
+                },
+            );
+        self.count += other.count;
+    }
+
+    fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn finalize(&self) -> Moments<Array<f64, D>> {
+        let count = self.count;
+        Moments {
+            count,
+            mean: self.mean.clone(),
+            variance: self
+                .squared_deviations
+                .mapv(|squared_deviations| variance_component(count, squared_deviations)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, Array1};
+
+    use super::*;
+    use crate::test_util::{accumulate, assert_close};
+
+    const SAMPLES: [f64; 7] = [3.5, -1.25, 8., 0., 2.75, 1e3, -4.5];
+
+    #[test]
+    fn merge_matches_single_pass() {
+        let n = SAMPLES.len() as f64;
+        let mean = SAMPLES.iter().sum::<f64>() / n;
+        let variance = SAMPLES.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.);
+        // Every split, including empty and single-sample sides.
+        for split in 0..=SAMPLES.len() {
+            let mut accumulator = accumulate(SAMPLES[..split].iter().copied());
+            accumulator.merge(accumulate(SAMPLES[split..].iter().copied()));
+            let moments = accumulator.finalize();
+            assert_eq!(moments.count(), SAMPLES.len() as u64);
+            assert_close(*moments.mean(), mean);
+            assert_close(*moments.variance(), variance);
+        }
+    }
+
+    #[test]
+    fn merge_of_single_samples() {
+        let mut accumulator = accumulate([2.]);
+        accumulator.merge(accumulate([6.]));
+        let moments = accumulator.finalize();
+        assert_close(*moments.mean(), 4.);
+        assert_close(*moments.variance(), 8.);
+    }
+
+    #[test]
+    fn merge_matches_single_pass_per_component() {
+        let samples: Vec<Array1<f64>> = SAMPLES.iter().map(|&x| array![x, x * x, 1.]).collect();
+        let single_pass = accumulate(samples.iter().cloned()).finalize();
+        for split in 0..=samples.len() {
+            let mut accumulator = accumulate(samples[..split].iter().cloned());
+            accumulator.merge(accumulate(samples[split..].iter().cloned()));
+            let moments = accumulator.finalize();
+            assert_eq!(moments.count(), single_pass.count());
+            for (actual, expected) in moments.mean().iter().zip(single_pass.mean()) {
+                assert_close(*actual, *expected);
+            }
+            for (actual, expected) in moments.variance().iter().zip(single_pass.variance()) {
+                assert_close(*actual, *expected);
+            }
+        }
+    }
+}
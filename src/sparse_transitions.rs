@@ -0,0 +1,513 @@
+use rand::Rng;
+use rand_distr::{Distribution, Exp};
+
+use crate::{
+    transient::uniformize, validate_generator, ContinuousMarkovTransitions, Matrix, Vector,
+};
+
+/// Largest number of Gauss-Seidel sweeps before the stationary solver gives up.
+const MAX_SWEEPS: usize = 100_000;
+
+/// Weight of the Gauss-Seidel update against the previous value. Plain Gauss-Seidel can oscillate
+/// between sweeps without converging when the jump chain is periodic, as in a tandem queue where
+/// mass circulates from one queue to the next. Mixing in a tenth of the previous value damps the
+/// oscillation while keeping most of the speed of a full update.
+const RELAXATION: f64 = 0.9;
+
+/// Generator of a CTMC storing only the positive off-diagonal rates, row by row (compressed sparse
+/// rows), for chains with too many states for [`crate::MatrixTransitions`].
+///
+/// Rows sum to zero: the diagonal is implied by the rates out of each state.
+#[derive(Debug, Clone)]
+pub struct SparseTransitions {
+    row_offsets: Vec<usize>,
+    columns: Vec<usize>,
+    rates: Vec<f64>,
+    cumulative_rates: Vec<f64>,
+    total_rates: Vector,
+}
+
+impl SparseTransitions {
+    /// The chain on `num_states` states with the `(from, to, rate)` off-diagonal rates. Rates
+    /// between the same pair of states are added up.
+    pub fn from_triplets(
+        num_states: usize,
+        triplets: impl IntoIterator<Item = (usize, usize, f64)>,
+    ) -> Self {
+        let mut triplets: Vec<_> = triplets
+            .into_iter()
+            .filter(|&(from, to, rate)| {
+                assert!(
+                    from < num_states && to < num_states,
+                    "Invalid transition from {from} to {to}. Maximum state is {}",
+                    num_states as i64 - 1
+                );
+                assert!(from != to, "Diagonal rates are implied. Got one for {from}");
+                assert!(
+                    rate >= 0. && rate.is_finite(),
+                    "Rates must be non-negative. Got {rate} from {from} to {to}"
+                );
+                rate > 0.
+            })
+            .collect();
+        triplets.sort_unstable_by_key(|&(from, to, _)| (from, to));
+
+        let mut row_offsets = vec![0; num_states + 1];
+        let mut columns = Vec::with_capacity(triplets.len());
+        let mut rates: Vec<f64> = Vec::with_capacity(triplets.len());
+        let mut previous = None;
+        for (from, to, rate) in triplets {
+            if previous == Some((from, to)) {
+                *rates.last_mut().unwrap() += rate;
+                continue;
+            }
+            previous = Some((from, to));
+            row_offsets[from + 1] += 1;
+            columns.push(to);
+            rates.push(rate);
+        }
+        for state in 0..num_states {
+            row_offsets[state + 1] += row_offsets[state];
+        }
+
+        let mut cumulative_rates = rates.clone();
+        let mut total_rates = Vector::zeros(num_states);
+        for state in 0..num_states {
+            let mut cumulative_rate = 0.;
+            for rate in &mut cumulative_rates[row_offsets[state]..row_offsets[state + 1]] {
+                cumulative_rate += *rate;
+                *rate = cumulative_rate;
+            }
+            total_rates[state] = cumulative_rate;
+        }
+        Self {
+            row_offsets,
+            columns,
+            rates,
+            cumulative_rates,
+            total_rates,
+        }
+    }
+
+    /// The chain with the given dense generator.
+    pub fn from_matrix(generator: &Matrix) -> Self {
+        validate_generator(generator);
+        Self::from_triplets(
+            generator.nrows(),
+            generator
+                .indexed_iter()
+                .filter(|&((from, to), &rate)| from != to && rate > 0.)
+                .map(|((from, to), &rate)| (from, to, rate)),
+        )
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.total_rates.len()
+    }
+
+    /// Number of stored off-diagonal rates.
+    pub fn num_transitions(&self) -> usize {
+        self.rates.len()
+    }
+
+    /// Total rate out of each state, i.e. the negated diagonal of the generator.
+    pub fn total_rates(&self) -> &Vector {
+        &self.total_rates
+    }
+
+    /// The states reachable in one jump from `state` and the rates to them, in increasing order of
+    /// state.
+    pub fn rates_from(&self, state: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let range = self.row_offsets[state]..self.row_offsets[state + 1];
+        self.columns[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.rates[range].iter().copied())
+    }
+
+    /// The dense generator, for small chains.
+    pub fn to_matrix(&self) -> Matrix {
+        let mut generator = Matrix::zeros((self.num_states(), self.num_states()));
+        for from in 0..self.num_states() {
+            for (to, rate) in self.rates_from(from) {
+                generator[[from, to]] = rate;
+            }
+            generator[[from, from]] = -self.total_rates[from];
+        }
+        generator
+    }
+
+    /// `distribution (I + Q / rate)`, one step of the chain uniformized at `rate`.
+    fn uniformized_step(&self, distribution: &Vector, rate: f64) -> Vector {
+        let mut next = distribution.clone();
+        for (from, &probability) in distribution.iter().enumerate() {
+            if probability == 0. {
+                continue;
+            }
+            for (to, transition_rate) in self.rates_from(from) {
+                next[to] += probability * transition_rate / rate;
+            }
+            next[from] -= probability * self.total_rates[from] / rate;
+        }
+        next
+    }
+
+    fn max_total_rate(&self) -> f64 {
+        self.total_rates
+            .iter()
+            .fold(0., |max: f64, &rate| max.max(rate))
+    }
+
+    fn check_distribution(&self, distribution: &Vector) {
+        assert_eq!(
+            distribution.len(),
+            self.num_states(),
+            "Distribution must have one entry per state"
+        );
+    }
+
+    /// Distribution at `time` of the chain started from `initial`, computed by uniformization to
+    /// within `tolerance` in total variation. See [`crate::transient_distribution`].
+    pub fn transient_distribution(&self, initial: &Vector, time: f64, tolerance: f64) -> Vector {
+        self.check_distribution(initial);
+        let rate = self.max_total_rate();
+        let jump = |distribution: &Vector| self.uniformized_step(distribution, rate);
+        uniformize(rate, jump, initial, None, time, tolerance).0
+    }
+
+    /// Expected reward accumulated over `[0, time]` by the chain started from `initial`. See
+    /// [`crate::expected_accumulated_reward`].
+    pub fn expected_accumulated_reward(
+        &self,
+        initial: &Vector,
+        reward: &Vector,
+        time: f64,
+        tolerance: f64,
+    ) -> f64 {
+        self.check_distribution(initial);
+        assert_eq!(
+            reward.len(),
+            self.num_states(),
+            "Reward must have one entry per state"
+        );
+        let rate = self.max_total_rate();
+        let jump = |distribution: &Vector| self.uniformized_step(distribution, rate);
+        uniformize(rate, jump, initial, Some(reward), time, tolerance).1
+    }
+
+    /// The stationary distribution of a chain with a single closed class by under-relaxed
+    /// Gauss-Seidel iteration on the balance equations from state 0. States outside the closed class
+    /// are transient and get probability zero, up to the tolerance.
+    ///
+    /// The iteration stops once the residual satisfies `‖πQ‖₁ <= tolerance * Λ`, where `Λ` is the
+    /// largest total rate out of a state. Then one step of the chain uniformized at `Λ` moves `π` by
+    /// at most `tolerance` in total variation. This bounds how far `π` is from balance, not from the
+    /// stationary distribution: on slowly mixing chains the distance can be larger by a factor of
+    /// the mixing time in uniformized steps.
+    ///
+    /// Panics if a state has no transitions, the chain has more than one closed class or the
+    /// iteration does not converge.
+    pub fn stationary_distribution(&self, tolerance: f64) -> Vector {
+        assert!(
+            tolerance > 0.,
+            "Tolerance must be positive. Got {tolerance}"
+        );
+        let n = self.num_states();
+        assert!(n > 0, "A chain must have at least one state");
+        if let Some(state) = (0..n).find(|&state| self.total_rates[state] == 0.) {
+            assert!(n == 1, "State {state} is absorbing");
+            return Vector::ones(1);
+        }
+
+        // Rates into each state, transposing the rows.
+        let mut incoming: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+        for from in 0..n {
+            for (to, rate) in self.rates_from(from) {
+                incoming[to].push((from, rate));
+            }
+        }
+        assert!(
+            has_single_closed_class(&incoming),
+            "Chain has more than one closed class, so the stationary distribution is not unique"
+        );
+        let inflow = |distribution: &Vector, state: usize| -> f64 {
+            incoming[state]
+                .iter()
+                .map(|&(from, rate)| distribution[from] * rate)
+                .sum()
+        };
+
+        let max_rate = self.max_total_rate();
+        let mut distribution = Vector::zeros(n);
+        distribution[0] = 1.;
+        for _ in 0..MAX_SWEEPS {
+            for state in 0..n {
+                distribution[state] = (1. - RELAXATION) * distribution[state]
+                    + RELAXATION * inflow(&distribution, state) / self.total_rates[state];
+            }
+            let total = distribution.sum();
+            distribution /= total;
+            let residual: f64 = (0..n)
+                .map(|state| {
+                    (inflow(&distribution, state) - distribution[state] * self.total_rates[state])
+                        .abs()
+                })
+                .sum();
+            if residual <= tolerance * max_rate {
+                return distribution;
+            }
+        }
+        panic!("Stationary distribution did not converge in {MAX_SWEEPS} sweeps");
+    }
+}
+
+/// Whether the chain with the given incoming transitions of each state has a single closed class,
+/// i.e. whether some state can be reached from every state.
+///
+/// The state finishing last in a depth-first search against the transitions lies in a closed class
+/// (as in Kosaraju's algorithm), so it is the only candidate to check.
+fn has_single_closed_class(incoming: &[Vec<(usize, f64)>]) -> bool {
+    let n = incoming.len();
+    let mut visited = vec![false; n];
+    let mut candidate = 0;
+    for root in 0..n {
+        if visited[root] {
+            continue;
+        }
+        visited[root] = true;
+        let mut stack = vec![(root, 0)];
+        while let Some(&(state, next)) = stack.last() {
+            match incoming[state].get(next) {
+                Some(&(from, _)) => {
+                    stack.last_mut().unwrap().1 += 1;
+                    if !visited[from] {
+                        visited[from] = true;
+                        stack.push((from, 0));
+                    }
+                }
+                None => {
+                    candidate = state;
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    let mut reaches_candidate = vec![false; n];
+    reaches_candidate[candidate] = true;
+    let mut stack = vec![candidate];
+    while let Some(state) = stack.pop() {
+        for &(from, _) in &incoming[state] {
+            if !reaches_candidate[from] {
+                reaches_candidate[from] = true;
+                stack.push(from);
+            }
+        }
+    }
+    reaches_candidate.into_iter().all(|reaches| reaches)
+}
+
+impl ContinuousMarkovTransitions for SparseTransitions {
+    type State = u64;
+
+    fn next_transition(
+        &mut self,
+        from_state: &u64,
+        _time: f64,
+        rng: &mut impl Rng,
+    ) -> Option<(u64, f64)> {
+        let from_state = *from_state as usize;
+        assert!(
+            from_state < self.num_states(),
+            "Invalid state. Maximum state is {}",
+            self.num_states() - 1
+        );
+        let total_rate = self.total_rates[from_state];
+        if total_rate == 0. {
+            // The process stays in an absorbing state forever.
+            return None;
+        }
+        let time_to_next_transition = Exp::new(total_rate).unwrap().sample(rng);
+
+        let range = self.row_offsets[from_state]..self.row_offsets[from_state + 1];
+        let cumulative_rates = &self.cumulative_rates[range.clone()];
+        let rng_value = rng.gen_range(0. ..total_rate);
+        let index = cumulative_rates
+            .partition_point(|&cumulative_rate| cumulative_rate <= rng_value)
+            .min(range.len() - 1);
+        Some((self.columns[range][index] as u64, time_to_next_transition))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+    use rand_pcg::Pcg64Mcg;
+
+    use super::*;
+    use crate::{expected_accumulated_reward, stationary_distribution, transient_distribution};
+
+    fn generator() -> Matrix {
+        array![
+            [-3., 1., 2., 0.],
+            [0., -1., 0.5, 0.5],
+            [4., 0., -5., 1.],
+            [0., 2., 0., -2.],
+        ]
+    }
+
+    fn birth_death(num_states: usize, birth_rate: f64, death_rate: f64) -> SparseTransitions {
+        SparseTransitions::from_triplets(
+            num_states,
+            (1..num_states).flat_map(|k| [(k - 1, k, birth_rate), (k, k - 1, death_rate)]),
+        )
+    }
+
+    #[test]
+    fn transient_distribution_matches_dense_solver() {
+        let generator = generator();
+        let sparse = SparseTransitions::from_matrix(&generator);
+        for initial in [array![1., 0., 0., 0.], array![0.1, 0.2, 0.3, 0.4]] {
+            for time in [0., 0.3, 2., 10.] {
+                let expected = transient_distribution(&generator, &initial, time, 1e-12);
+                let distribution = sparse.transient_distribution(&initial, time, 1e-12);
+                assert!(
+                    (&distribution - &expected).mapv(f64::abs).sum() < 1e-9,
+                    "At {time}: {distribution} vs {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn expected_accumulated_reward_matches_dense_solver() {
+        let generator = generator();
+        let sparse = SparseTransitions::from_matrix(&generator);
+        let initial = array![0., 1., 0., 0.];
+        let reward = array![1., 0., 3., -2.];
+        for time in [0., 0.3, 2., 10.] {
+            let expected = expected_accumulated_reward(&generator, &initial, &reward, time, 1e-12);
+            let accumulated = sparse.expected_accumulated_reward(&initial, &reward, time, 1e-12);
+            assert!(
+                (accumulated - expected).abs() < 1e-9,
+                "At {time}: {accumulated} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn next_transition_follows_the_rates() {
+        let generator = generator();
+        let mut sparse = SparseTransitions::from_matrix(&generator);
+        let mut rng = Pcg64Mcg::new(0);
+        let samples = 100_000;
+        for from in 0..generator.nrows() {
+            let mut counts = vec![0; generator.nrows()];
+            let mut holding_time = 0.;
+            for _ in 0..samples {
+                let (to, time) = sparse
+                    .next_transition(&(from as u64), 0., &mut rng)
+                    .unwrap();
+                counts[to as usize] += 1;
+                holding_time += time;
+            }
+            let total_rate = -generator[[from, from]];
+            for (to, &count) in counts.iter().enumerate() {
+                let expected = if to == from {
+                    0.
+                } else {
+                    generator[[from, to]] / total_rate
+                };
+                let frequency = count as f64 / samples as f64;
+                assert!(
+                    (frequency - expected).abs() < 0.01,
+                    "From {from} to {to}: {frequency} vs {expected}"
+                );
+            }
+            let mean_holding_time = holding_time / samples as f64;
+            assert!(
+                (mean_holding_time * total_rate - 1.).abs() < 0.02,
+                "From {from}: mean holding time {mean_holding_time} vs {}",
+                1. / total_rate
+            );
+        }
+    }
+
+    #[test]
+    fn next_transition_stops_in_absorbing_state() {
+        let mut sparse = SparseTransitions::from_triplets(2, [(0, 1, 1.)]);
+        assert!(sparse
+            .next_transition(&1, 0., &mut Pcg64Mcg::new(0))
+            .is_none());
+    }
+
+    #[test]
+    fn stationary_distribution_matches_dense_solver() {
+        let generator = generator();
+        let sparse = SparseTransitions::from_matrix(&generator);
+        let expected = stationary_distribution(&generator);
+        let distribution = sparse.stationary_distribution(1e-12);
+        assert!((&distribution - &expected).mapv(f64::abs).sum() < 1e-9);
+    }
+
+    #[test]
+    fn stationary_distribution_matches_product_form() {
+        // M/M/1/K: geometric with ratio ρ, truncated.
+        let (num_states, rho) = (200, 0.95);
+        let distribution = birth_death(num_states, rho, 1.).stationary_distribution(1e-12);
+        let normalization = (1. - rho.powi(num_states as i32)) / (1. - rho);
+        for (k, &probability) in distribution.iter().enumerate() {
+            let expected = rho.powi(k as i32) / normalization;
+            assert!(
+                (probability - expected).abs() < 1e-8,
+                "State {k}: {probability} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn stationary_distribution_meets_residual_tolerance() {
+        let transitions = birth_death(300, 0.99, 1.);
+        let tolerance = 1e-8;
+        let distribution = transitions.stationary_distribution(tolerance);
+        let residual = distribution
+            .dot(&transitions.to_matrix())
+            .mapv(f64::abs)
+            .sum();
+        assert!(residual <= tolerance * transitions.max_total_rate());
+    }
+
+    #[test]
+    fn stationary_distribution_ignores_transient_states() {
+        // States 0 and 3 are transient, the closed class is {1, 2}.
+        let generator = array![
+            [-2., 1., 0., 1.],
+            [0., -1., 1., 0.],
+            [0., 3., -3., 0.],
+            [1., 0., 1., -2.],
+        ];
+        let distribution =
+            SparseTransitions::from_matrix(&generator).stationary_distribution(1e-12);
+        let expected = stationary_distribution(&generator);
+        assert!((&distribution - &expected).mapv(f64::abs).sum() < 1e-9);
+        assert!(distribution[0] < 1e-9 && distribution[3] < 1e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "more than one closed class")]
+    fn stationary_distribution_requires_one_closed_class() {
+        // {0, 1} and {3, 4} are closed, 2 leads to both.
+        SparseTransitions::from_triplets(
+            5,
+            [
+                (0, 1, 1.),
+                (1, 0, 2.),
+                (2, 1, 1.),
+                (2, 3, 1.),
+                (3, 4, 3.),
+                (4, 3, 1.),
+            ],
+        )
+        .stationary_distribution(1e-12);
+    }
+}
@@ -0,0 +1,279 @@
+use ndarray::ArrayView1;
+
+use crate::{linalg, Matrix, Vector};
+
+/// A maximal set of states that can all reach each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommunicatingClass {
+    states: Vec<usize>,
+    closed: bool,
+}
+
+impl CommunicatingClass {
+    /// The states of the class in increasing order.
+    pub fn states(&self) -> &[usize] {
+        &self.states
+    }
+
+    /// Whether the class cannot be left, i.e. is recurrent.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+/// The stationary distribution of a chain started in a closed class.
+#[derive(Debug, Clone)]
+pub struct StationaryDistribution {
+    class: CommunicatingClass,
+    distribution: Vector,
+}
+
+impl StationaryDistribution {
+    pub fn class(&self) -> &CommunicatingClass {
+        &self.class
+    }
+
+    /// Probabilities of all states of the chain, zero outside the class.
+    pub fn distribution(&self) -> &Vector {
+        &self.distribution
+    }
+}
+
+/// Panics unless `generator` is square with non-negative off-diagonal entries and rows summing to
+/// zero.
+pub fn validate_generator(generator: &Matrix) {
+    validate_subgenerator(generator);
+    for (i, row) in generator.outer_iter().enumerate() {
+        let sum = row.sum();
+        assert!(
+            sum >= -1e-9 * row_scale(row),
+            "Rows of a generator must sum to zero. Row {i} sums to {sum}"
+        );
+    }
+}
+
+fn row_scale(row: ArrayView1<f64>) -> f64 {
+    row.iter().fold(1., |max: f64, value| max.max(value.abs()))
+}
+
+/// Panics unless `generator` is square with non-negative off-diagonal entries and rows summing to
+/// at most zero.
+///
+/// Rows summing to less than zero are allowed: as in [`crate::MatrixTransitions`] the missing rate
+/// leads to an implicit absorbing state, so distributions lose mass over time.
+pub(crate) fn validate_subgenerator(generator: &Matrix) {
+    assert!(generator.is_square(), "Generator must be square");
+    for (i, row) in generator.outer_iter().enumerate() {
+        for (j, &rate) in row.iter().enumerate() {
+            assert!(
+                i == j || rate >= 0.,
+                "Off-diagonal rates must be non-negative. Got {rate} from {i} to {j}"
+            );
+        }
+        let sum = row.sum();
+        assert!(
+            sum <= 1e-9 * row_scale(row),
+            "Rows of a generator must not sum to more than zero. Row {i} sums to {sum}"
+        );
+    }
+}
+
+/// Panics unless `transitions` is square with non-negative entries and rows summing to at most one,
+/// the missing probability leading to an implicit absorbing state.
+pub(crate) fn validate_substochastic(transitions: &Matrix) {
+    assert!(transitions.is_square(), "Transition matrix must be square");
+    for (i, row) in transitions.outer_iter().enumerate() {
+        for (j, &probability) in row.iter().enumerate() {
+            assert!(
+                probability >= 0.,
+                "Transition probabilities must be non-negative. Got {probability} from {i} to {j}"
+            );
+        }
+        let sum = row.sum();
+        assert!(
+            sum <= 1. + 1e-9,
+            "Rows of a transition matrix must not sum to more than one. Row {i} sums to {sum}"
+        );
+    }
+}
+
+/// Communicating classes of the chain with the given generator or transition matrix, ordered by
+/// their smallest state. Only the pattern of positive off-diagonal entries is used.
+pub fn communicating_classes(transitions: &Matrix) -> Vec<CommunicatingClass> {
+    assert!(transitions.is_square(), "Transition matrix must be square");
+    let n = transitions.nrows();
+    let reachable: Vec<Vec<bool>> = (0..n)
+        .map(|start| {
+            let mut reached = vec![false; n];
+            reached[start] = true;
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                for (j, &rate) in transitions.row(i).iter().enumerate() {
+                    if i != j && rate > 0. && !reached[j] {
+                        reached[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+            reached
+        })
+        .collect();
+
+    let mut assigned = vec![false; n];
+    let mut classes = Vec::new();
+    for i in 0..n {
+        if assigned[i] {
+            continue;
+        }
+        let states: Vec<_> = (i..n)
+            .filter(|&j| reachable[i][j] && reachable[j][i])
+            .collect();
+        for &j in &states {
+            assigned[j] = true;
+        }
+        let closed = states
+            .iter()
+            .all(|&j| (0..n).all(|k| !reachable[j][k] || states.binary_search(&k).is_ok()));
+        classes.push(CommunicatingClass { states, closed });
+    }
+    classes
+}
+
+/// Solves `pi Q = 0` with `pi` summing to one on the states of a closed class.
+fn stationary_on_class(generator: &Matrix, class: &CommunicatingClass) -> Vector {
+    let states = class.states();
+    let m = states.len();
+    let mut system = Matrix::from_shape_fn((m, m), |(r, c)| generator[[states[c], states[r]]]);
+    system.row_mut(m - 1).fill(1.);
+    let mut rhs = Vector::zeros(m);
+    rhs[m - 1] = 1.;
+    let restricted = linalg::solve(&system, &rhs);
+
+    let mut distribution = Vector::zeros(generator.nrows());
+    for (&state, &probability) in states.iter().zip(&restricted) {
+        distribution[state] = probability;
+    }
+    distribution
+}
+
+/// The stationary distribution of every closed class of the CTMC with the given generator.
+///
+/// Every stationary distribution of the chain is a mixture of these.
+pub fn stationary_distributions(generator: &Matrix) -> Vec<StationaryDistribution> {
+    validate_generator(generator);
+    communicating_classes(generator)
+        .into_iter()
+        .filter(CommunicatingClass::is_closed)
+        .map(|class| StationaryDistribution {
+            distribution: stationary_on_class(generator, &class),
+            class,
+        })
+        .collect()
+}
+
+/// The unique stationary distribution of a CTMC with a single closed class.
+pub fn stationary_distribution(generator: &Matrix) -> Vector {
+    let mut distributions = stationary_distributions(generator);
+    assert_eq!(
+        distributions.len(),
+        1,
+        "Chain has {} closed classes, so the stationary distribution is not unique",
+        distributions.len()
+    );
+    distributions.remove(0).distribution
+}
+
+/// The deviation matrix `D = ∫ (e^{Qt} - 1π) dt` of a CTMC with a single closed class, computed as
+/// `(1π - Q)⁻¹ - 1π`.
+///
+/// It gives the long-run effect of the starting state, e.g. `∫₀^T e^{Qt} dt = T 1π + D - e^{QT} D`,
+/// and with it the variances of time integrals and transition counts over long windows.
+pub fn deviation_matrix(generator: &Matrix) -> Matrix {
+    let distribution = stationary_distribution(generator);
+    let n = generator.nrows();
+    let limit = Matrix::from_shape_fn((n, n), |(_, j)| distribution[j]);
+    linalg::solve_matrix(&(&limit - generator), &Matrix::eye(n)) - limit
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::test_util::assert_close_vectors;
+
+    /// Closed classes {0, 1} and {3, 4}, with 2 transient and leaving into both.
+    fn reducible_generator() -> Matrix {
+        array![
+            [-1., 1., 0., 0., 0.],
+            [2., -2., 0., 0., 0.],
+            [0., 1., -3., 2., 0.],
+            [0., 0., 0., -3., 3.],
+            [0., 0., 0., 1., -1.],
+        ]
+    }
+
+    #[test]
+    fn communicating_classes_of_reducible_chain() {
+        let classes = communicating_classes(&reducible_generator());
+        let partition: Vec<_> = classes
+            .iter()
+            .map(|class| (class.states().to_vec(), class.is_closed()))
+            .collect();
+        assert_eq!(
+            partition,
+            [(vec![0, 1], true), (vec![2], false), (vec![3, 4], true)]
+        );
+    }
+
+    #[test]
+    fn stationary_distribution_per_closed_class() {
+        let distributions = stationary_distributions(&reducible_generator());
+        assert_eq!(distributions.len(), 2);
+        assert_eq!(distributions[0].class().states(), [0, 1]);
+        assert_close_vectors(
+            distributions[0].distribution(),
+            &array![2. / 3., 1. / 3., 0., 0., 0.],
+        );
+        assert_eq!(distributions[1].class().states(), [3, 4]);
+        assert_close_vectors(
+            distributions[1].distribution(),
+            &array![0., 0., 0., 0.25, 0.75],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "not unique")]
+    fn stationary_distribution_requires_one_closed_class() {
+        stationary_distribution(&reducible_generator());
+    }
+
+    #[test]
+    #[should_panic(expected = "Off-diagonal rates must be non-negative")]
+    fn validate_generator_rejects_negative_rates() {
+        validate_generator(&array![[1., -1.], [1., -1.]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "must not sum to more than zero")]
+    fn validate_generator_rejects_positive_row_sums() {
+        validate_generator(&array![[-1., 2.], [1., -1.]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "must sum to zero")]
+    fn validate_generator_rejects_negative_row_sums() {
+        validate_generator(&array![[-2., 1.], [1., -1.]]);
+    }
+
+    #[test]
+    fn deviation_matrix_of_two_state_chain() {
+        // With rates a and b out of the states, e^{Qt} - 1π = e^{-(a+b)t} (I - 1π).
+        let (a, b) = (1., 3.);
+        let generator = array![[-a, a], [b, -b]];
+        let limit = array![[b, a], [b, a]] / (a + b);
+        let expected = (Matrix::eye(2) - limit) / (a + b);
+        let deviation = deviation_matrix(&generator);
+        assert!((&deviation - &expected).mapv(f64::abs).sum() < 1e-12);
+    }
+}
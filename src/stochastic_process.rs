@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use rand::Rng;
+
+use crate::{window, Sample};
+
+/// A process in continuous time that changes at discrete events, e.g. the jumps of a Markov chain,
+/// the steps of a discrete-time chain, the arrivals and departures of a queue or the steps of a
+/// discretised diffusion.
+pub trait StochasticProcess {
+    type State: Clone;
+
+    fn time(&self) -> f64;
+
+    fn state(&self) -> Self::State;
+
+    /// Moves the process to its next event, returning `false` if it will not change again.
+    fn next_event(&mut self, rng: &mut impl Rng) -> bool;
+
+    /// Moves the process to exactly `time`, which must not be before the current time.
+    fn advance_to(&mut self, time: f64, rng: &mut impl Rng);
+
+    /// The time and state after each event from now on, ending if the process stops changing.
+    fn events<'a, R: Rng>(&'a mut self, rng: &'a mut R) -> Events<'a, Self, R>
+    where
+        Self: Sized,
+    {
+        Events { process: self, rng }
+    }
+
+    /// The states at each of the increasing `times`.
+    fn sample_at(&mut self, times: &[f64], rng: &mut impl Rng) -> Vec<Self::State>
+    where
+        Self: Sized,
+    {
+        times
+            .iter()
+            .map(|&time| {
+                self.advance_to(time, rng);
+                self.state()
+            })
+            .collect()
+    }
+
+    /// Runs the process through `[start, end]` and returns the time average of `f` of the state
+    /// over that window, the state being constant between events. For a jump process this is exact,
+    /// e.g. with an indicator function the fraction of time spent in a set of states. For a
+    /// discretised diffusion it holds each step's value until the next step, an approximation
+    /// whose error depends on the time step.
+    ///
+    /// The window must not start before the current time. The process is left at its first event
+    /// after `end`.
+    fn time_average<S: Sample>(
+        &mut self,
+        f: impl Fn(Self::State) -> S,
+        start: f64,
+        end: f64,
+        rng: &mut impl Rng,
+    ) -> S
+    where
+        Self: Sized,
+    {
+        let position = (self.time(), self.state());
+        let next_jump = || self.next_event(rng).then(|| (self.time(), self.state()));
+        window::time_average(position, next_jump, (start, end), f)
+    }
+
+    /// Runs the process through `[start, end]` and returns the fraction of that window spent in
+    /// each visited state. See [`StochasticProcess::time_average`].
+    fn occupation(&mut self, start: f64, end: f64, rng: &mut impl Rng) -> BTreeMap<Self::State, f64>
+    where
+        Self: Sized,
+        Self::State: Ord,
+    {
+        let position = (self.time(), self.state());
+        let next_jump = || self.next_event(rng).then(|| (self.time(), self.state()));
+        window::occupation(position, next_jump, (start, end))
+    }
+
+    /// Runs the process event by event until `predicate` holds for its state and returns the time,
+    /// or `None` if it does not hold at an event up to `deadline`.
+    ///
+    /// The state is only checked at events, so a discretised diffusion may cross in between. The
+    /// process may be left past the deadline.
+    fn hitting_time(
+        &mut self,
+        predicate: impl Fn(Self::State) -> bool,
+        deadline: f64,
+        rng: &mut impl Rng,
+    ) -> Option<f64>
+    where
+        Self: Sized,
+    {
+        while self.time() <= deadline {
+            if predicate(self.state()) {
+                return Some(self.time());
+            }
+            if !self.next_event(rng) {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// Iterator over the events of a process, see [`StochasticProcess::events`].
+#[derive(Debug)]
+pub struct Events<'a, P, R> {
+    process: &'a mut P,
+    rng: &'a mut R,
+}
+
+impl<P, R> Iterator for Events<'_, P, R>
+where
+    P: StochasticProcess,
+    R: Rng,
+{
+    type Item = (f64, P::State);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.process
+            .next_event(self.rng)
+            .then(|| (self.process.time(), self.process.state()))
+    }
+}
@@ -0,0 +1,317 @@
+use std::fmt::{self, Debug, Display};
+
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::{test_theory, ExperimentRng, Sample, TestTheoryResult};
+
+/// The result of [`test_theory`] at one point of a parameter sweep.
+#[derive(Debug, Clone)]
+pub struct SweepPoint<X, S: Sample> {
+    value: X,
+    labels: Vec<String>,
+    seed: u64,
+    result: TestTheoryResult<S>,
+}
+
+impl<X, S> SweepPoint<X, S>
+where
+    S: Sample,
+{
+    /// The swept value, a pair for two-dimensional sweeps.
+    pub fn value(&self) -> &X {
+        &self.value
+    }
+
+    /// The swept value formatted per axis.
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// Seed of the rng the point was simulated with, so it can be rerun on its own.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn result(&self) -> &TestTheoryResult<S> {
+        &self.result
+    }
+}
+
+/// Theory and simulation over a one- or two-dimensional grid of parameter values.
+#[derive(Debug, Clone)]
+pub struct Sweep<X, S: Sample> {
+    axes: Vec<&'static str>,
+    points: Vec<SweepPoint<X, S>>,
+}
+
+impl<X, S> Sweep<X, S>
+where
+    S: Sample,
+{
+    pub fn axes(&self) -> &[&'static str] {
+        &self.axes
+    }
+
+    pub fn points(&self) -> &[SweepPoint<X, S>] {
+        &self.points
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_sweep<X, P, E, S, T, M, R>(
+    experiment: &E,
+    theory: &T,
+    axes: Vec<&'static str>,
+    values: Vec<(X, Vec<String>)>,
+    make_parameters: M,
+    samples: u32,
+    rng: &mut R,
+) -> Sweep<X, S>
+where
+    P: Sync + Debug + Serialize,
+    E: Fn(&P, &mut ExperimentRng) -> S + Sync + Send,
+    S: Sample,
+    T: Fn(&P) -> S,
+    M: Fn(&X) -> P,
+    R: Rng,
+{
+    let seeds: Vec<_> = values.iter().map(|_| rng.next_u64()).collect();
+    let points = values
+        .into_iter()
+        .zip(seeds)
+        .map(|((value, labels), seed)| {
+            let parameters = make_parameters(&value);
+            let mut rng = ExperimentRng::seed_from_u64(seed);
+            let result = test_theory(experiment, theory, &parameters, samples, &mut rng);
+            SweepPoint {
+                value,
+                labels,
+                seed,
+                result,
+            }
+        })
+        .collect();
+    Sweep { axes, points }
+}
+
+/// Runs [`test_theory`] for every value of a single parameter.
+///
+/// Each value gets its own seed drawn in order from `rng`, so a point's result does not depend on
+/// which other values are in the sweep after it.
+pub fn sweep<X, P, E, S, T, M, R>(
+    experiment: E,
+    theory: T,
+    axis: &'static str,
+    values: impl IntoIterator<Item = X>,
+    make_parameters: M,
+    samples: u32,
+    rng: &mut R,
+) -> Sweep<X, S>
+where
+    X: Display,
+    P: Sync + Debug + Serialize,
+    E: Fn(&P, &mut ExperimentRng) -> S + Sync + Send,
+    S: Sample,
+    T: Fn(&P) -> S,
+    M: Fn(&X) -> P,
+    R: Rng,
+{
+    let values = values
+        .into_iter()
+        .map(|value| {
+            let labels = vec![value.to_string()];
+            (value, labels)
+        })
+        .collect();
+    run_sweep(
+        &experiment,
+        &theory,
+        vec![axis],
+        values,
+        make_parameters,
+        samples,
+        rng,
+    )
+}
+
+/// Runs [`test_theory`] for every combination of values of two parameters, the second axis varying
+/// fastest.
+#[allow(clippy::too_many_arguments)]
+pub fn sweep_grid<X, Y, P, E, S, T, M, R>(
+    experiment: E,
+    theory: T,
+    (x_axis, x_values): (&'static str, impl IntoIterator<Item = X>),
+    (y_axis, y_values): (&'static str, impl IntoIterator<Item = Y>),
+    make_parameters: M,
+    samples: u32,
+    rng: &mut R,
+) -> Sweep<(X, Y), S>
+where
+    X: Display + Clone,
+    Y: Display + Clone,
+    P: Sync + Debug + Serialize,
+    E: Fn(&P, &mut ExperimentRng) -> S + Sync + Send,
+    S: Sample,
+    T: Fn(&P) -> S,
+    M: Fn(&X, &Y) -> P,
+    R: Rng,
+{
+    let y_values: Vec<_> = y_values.into_iter().collect();
+    let values = x_values
+        .into_iter()
+        .flat_map(|x| {
+            y_values.iter().map(move |y| {
+                let labels = vec![x.to_string(), y.to_string()];
+                ((x.clone(), y.clone()), labels)
+            })
+        })
+        .collect();
+    run_sweep(
+        &experiment,
+        &theory,
+        vec![x_axis, y_axis],
+        values,
+        |(x, y)| make_parameters(x, y),
+        samples,
+        rng,
+    )
+}
+
+impl<X, S> Display for Sweep<X, S>
+where
+    S: Sample,
+{
+    /// One line per point and component, flagging components that disagree at significance 0.05.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for axis in &self.axes {
+            write!(f, "{axis:>12}  ")?;
+        }
+        writeln!(
+            f,
+            "{:>12}  {:>12}  {:>12}  {:>12}  {:>9}  {:>9}",
+            "component", "theory", "empirical", "std. error", "z-score", "p-value"
+        )?;
+        for point in &self.points {
+            for row in point.result.comparison(0.05).rows() {
+                for label in &point.labels {
+                    write!(f, "{label:>12}  ")?;
+                }
+                writeln!(
+                    f,
+                    "{:>12}  {:>12.6}  {:>12.6}  {:>12.6}  {:>9.3}  {:>9.4}{}",
+                    row.name,
+                    row.theory,
+                    row.empirical,
+                    row.standard_error,
+                    row.z_score,
+                    row.p_value,
+                    if row.disagrees { "  *" } else { "" }
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+    use rand_pcg::Pcg64Mcg;
+
+    use super::*;
+
+    fn experiment(&(x, y): &(f64, f64), rng: &mut ExperimentRng) -> f64 {
+        x + y * rng.gen::<f64>()
+    }
+
+    fn theory(&(x, y): &(f64, f64)) -> f64 {
+        x + y / 2.
+    }
+
+    fn mean_bits<X>(sweep: &Sweep<X, f64>) -> Vec<u64> {
+        sweep
+            .points()
+            .iter()
+            .map(|point| point.result().parts().1.to_bits())
+            .collect()
+    }
+
+    fn run(values: &[f64]) -> Sweep<f64, f64> {
+        let values = values.to_vec();
+        sweep(
+            experiment,
+            theory,
+            "x",
+            values,
+            |&x| (x, 1.),
+            100,
+            &mut Pcg64Mcg::new(7),
+        )
+    }
+
+    #[test]
+    fn points_are_seeded_in_order() {
+        let sweep = run(&[1., 2., 3.]);
+        let mut rng = Pcg64Mcg::new(7);
+        for point in sweep.points() {
+            assert_eq!(point.seed(), rng.next_u64());
+            // test_theory draws the seed of its run from the point's rng.
+            let run_seed = ExperimentRng::seed_from_u64(point.seed()).next_u64();
+            assert_eq!(point.result().seed(), run_seed);
+        }
+        assert_eq!(mean_bits(&run(&[1., 2., 3.])), mean_bits(&sweep));
+    }
+
+    #[test]
+    fn points_can_be_rerun_on_their_own() {
+        let sweep = run(&[1., 2., 3.]);
+        let point = &sweep.points()[1];
+        let result = test_theory(
+            experiment,
+            theory,
+            &(2., 1.),
+            100,
+            &mut ExperimentRng::seed_from_u64(point.seed()),
+        );
+        assert_eq!(
+            result.parts().1.to_bits(),
+            point.result().parts().1.to_bits()
+        );
+        // Later values do not change earlier points.
+        assert_eq!(mean_bits(&run(&[1., 2.])), mean_bits(&sweep)[..2]);
+    }
+
+    #[test]
+    fn grid_varies_the_second_axis_fastest() {
+        let sweep = sweep_grid(
+            experiment,
+            theory,
+            ("x", [1, 2]),
+            ("y", [0.5, 0.25, 0.125]),
+            |&x, &y| (x as f64, y),
+            100,
+            &mut Pcg64Mcg::new(7),
+        );
+        assert_eq!(sweep.axes(), ["x", "y"]);
+        let values: Vec<_> = sweep.points().iter().map(|point| *point.value()).collect();
+        assert_eq!(
+            values,
+            [
+                (1, 0.5),
+                (1, 0.25),
+                (1, 0.125),
+                (2, 0.5),
+                (2, 0.25),
+                (2, 0.125)
+            ]
+        );
+        let labels: Vec<_> = sweep.points().iter().map(|point| point.labels()).collect();
+        assert_eq!(labels[0], ["1", "0.5"]);
+        assert_eq!(labels[5], ["2", "0.125"]);
+        for point in sweep.points() {
+            let (x, y) = *point.value();
+            assert_eq!(point.result().parts().0, &theory(&(x as f64, y)));
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Helpers shared by the unit tests.
+
+use crate::{Accumulator, Sample, Vector};
+
+/// Pushes the samples in order into a fresh accumulator.
+pub(crate) fn accumulate<S: Sample>(samples: impl IntoIterator<Item = S>) -> S::Accumulator {
+    let mut accumulator = S::Accumulator::zero();
+    for sample in samples {
+        accumulator.push(&sample);
+    }
+    accumulator
+}
+
+/// Asserts that `actual` agrees with `expected` to a relative `1e-9`, or an absolute `1e-9` for
+/// values below one. Loose enough for truncated series and linear solves.
+pub(crate) fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() <= 1e-9 * expected.abs().max(1.),
+        "{actual} vs {expected}"
+    );
+}
+
+/// Asserts that two vectors, typically distributions, are within `1e-12` of each other in the
+/// 1-norm.
+pub(crate) fn assert_close_vectors(actual: &Vector, expected: &Vector) {
+    assert!(
+        (actual - expected).mapv(f64::abs).sum() < 1e-12,
+        "{actual} vs {expected}"
+    );
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+/// Recorded path of a jump process as the sequence of (jump time, state) pairs, starting with the
+/// initial state at the initial time.
+///
+/// The path is right-continuous: at a jump time it already takes the new state. It is known up to
+/// the time of the last recorded jump, after which it is assumed to stay in the last state, so
+/// queries should not reach past the time the process was simulated to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory<T = u64> {
+    jumps: Vec<(f64, T)>,
+}
+
+impl<T: Clone> Trajectory<T> {
+    pub fn new(start_time: f64, start_state: T) -> Self {
+        Self {
+            jumps: vec![(start_time, start_state)],
+        }
+    }
+
+    pub(crate) fn push(&mut self, time: f64, state: T) {
+        debug_assert!(time >= self.end_time(), "Jumps must be recorded in order.");
+        self.jumps.push((time, state));
+    }
+
+    /// All (jump time, state) pairs, the first being the initial state.
+    pub fn jumps(&self) -> &[(f64, T)] {
+        &self.jumps
+    }
+
+    pub fn start_time(&self) -> f64 {
+        self.jumps[0].0
+    }
+
+    /// Time of the last recorded jump.
+    pub fn end_time(&self) -> f64 {
+        self.jumps[self.jumps.len() - 1].0
+    }
+
+    /// Number of jumps, not counting the initial state.
+    pub fn num_jumps(&self) -> usize {
+        self.jumps.len() - 1
+    }
+
+    /// Index of the last jump at or before `time`.
+    fn index_at(&self, time: f64) -> usize {
+        assert!(
+            time >= self.start_time(),
+            "Time {time} is before the start of the path at {}",
+            self.start_time()
+        );
+        self.jumps
+            .partition_point(|&(jump_time, _)| jump_time <= time)
+            - 1
+    }
+
+    /// State of the path at `time`.
+    pub fn state_at(&self, time: f64) -> T {
+        self.jumps[self.index_at(time)].1.clone()
+    }
+
+    /// Number of times the path enters the set of states accepted by `in_set` at a time in
+    /// `[start, end)`. Being in the set at the start of the path counts as an entry.
+    pub fn visits(&self, in_set: impl Fn(T) -> bool, start: f64, end: f64) -> usize {
+        let mut was_in_set = false;
+        let mut visits = 0;
+        for (time, state) in &self.jumps {
+            let time = *time;
+            if time >= end {
+                break;
+            }
+            let is_in_set = in_set(state.clone());
+            if is_in_set && !was_in_set && time >= start {
+                visits += 1;
+            }
+            was_in_set = is_in_set;
+        }
+        visits
+    }
+
+    /// Time spent in each visited state during `[start, end]`.
+    pub fn occupation_times(&self, start: f64, end: f64) -> BTreeMap<T, f64>
+    where
+        T: Ord,
+    {
+        assert!(start <= end, "Window must not end before it starts.");
+        let mut occupation_times = BTreeMap::new();
+        let first = self.index_at(start);
+        for (index, (time, state)) in self.jumps.iter().enumerate().skip(first) {
+            let time = *time;
+            if time >= end {
+                break;
+            }
+            let next_time = self
+                .jumps
+                .get(index + 1)
+                .map_or(end, |&(next_time, _)| next_time.min(end));
+            *occupation_times.entry(state.clone()).or_insert(0.) += next_time - time.max(start);
+        }
+        occupation_times
+    }
+
+    /// Time spent in the set of states accepted by `in_set` during `[start, end]`.
+    pub fn time_in(&self, in_set: impl Fn(T) -> bool, start: f64, end: f64) -> f64
+    where
+        T: Ord,
+    {
+        self.occupation_times(start, end)
+            .into_iter()
+            .filter_map(|(state, time)| in_set(state).then_some(time))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 0 until 1, 1 until 2, 2 until 2.5, 1 until 4, 0 until 5, then 1.
+    fn path() -> Trajectory {
+        let mut path = Trajectory::new(0., 0);
+        for (time, state) in [(1., 1), (2., 2), (2.5, 1), (4., 0), (5., 1)] {
+            path.push(time, state);
+        }
+        path
+    }
+
+    #[test]
+    fn state_at_is_right_continuous() {
+        let path = path();
+        let states: Vec<_> = [0., 0.999, 1., 1.5, 2., 2.5, 3.999, 4., 5., 7.]
+            .into_iter()
+            .map(|time| path.state_at(time))
+            .collect();
+        assert_eq!(states, [0, 0, 1, 1, 2, 1, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "before the start of the path")]
+    fn state_before_the_start_panics() {
+        path().state_at(-0.5);
+    }
+
+    #[test]
+    fn visits_count_entries_into_the_set() {
+        let path = path();
+        assert_eq!(path.visits(|state| state == 1, 0., 10.), 3);
+        // Leaving 1 for 2 and coming back stays inside the set {1, 2}.
+        assert_eq!(path.visits(|state| state >= 1, 0., 10.), 2);
+        // The start of the path counts as an entry.
+        assert_eq!(path.visits(|state| state == 0, 0., 10.), 2);
+        assert_eq!(path.visits(|state| state == 3, 0., 10.), 0);
+    }
+
+    #[test]
+    fn visits_count_entries_inside_the_window() {
+        let path = path();
+        // An entry at the start of the window counts, one at its end does not.
+        assert_eq!(path.visits(|state| state == 1, 1., 5.), 2);
+        // Being in the set when the window starts is not an entry.
+        assert_eq!(path.visits(|state| state == 1, 1.5, 10.), 2);
+        assert_eq!(path.visits(|state| state == 0, 0.5, 10.), 1);
+        assert_eq!(path.visits(|state| state == 1, 3., 4.5), 0);
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{linalg, stationary::validate_subgenerator, Matrix, Vector};
+
+/// Largest expected number of uniformized jumps per step, keeping the Poisson weights clear of
+/// underflow.
+const MAX_JUMPS_PER_STEP: f64 = 400.;
+
+/// Advances `distribution` by `time` with uniformization at `rate`, returning the new distribution
+/// and the integral of `reward` against the distribution over that time. `jump` multiplies a
+/// distribution by the uniformized transition matrix `I + Q / rate`.
+///
+/// Both are accurate to within `tolerance`, in total variation and absolute error respectively.
+pub(crate) fn uniformize(
+    rate: f64,
+    jump: impl Fn(&Vector) -> Vector,
+    distribution: &Vector,
+    reward: Option<&Vector>,
+    time: f64,
+    tolerance: f64,
+) -> (Vector, f64) {
+    assert!(time >= 0., "Time must be non-negative. Got {time}");
+    assert!(
+        tolerance > 0.,
+        "Tolerance must be positive. Got {tolerance}"
+    );
+    let reward_scale = reward.map_or(0., |reward| {
+        reward
+            .iter()
+            .fold(0., |max: f64, value| max.max(value.abs()))
+    });
+    if rate == 0. || time == 0. {
+        let accumulated = reward.map_or(0., |reward| distribution.dot(reward) * time);
+        return (distribution.clone(), accumulated);
+    }
+
+    let num_steps = (rate * time / MAX_JUMPS_PER_STEP).ceil();
+    let step_time = time / num_steps;
+    let step_tolerance = tolerance / num_steps;
+    let mean_jumps = rate * step_time;
+    // Mass dropped by truncating a step is missing from the reward of all later steps, so with a
+    // reward half the tolerance goes to that and half to truncating the step's own reward.
+    let (distribution_tolerance, reward_tolerance) = if reward.is_some() {
+        (
+            step_tolerance / (2. * reward_scale * time).max(1.),
+            step_tolerance / 2.,
+        )
+    } else {
+        (step_tolerance, step_tolerance)
+    };
+
+    let mut distribution = distribution.clone();
+    let mut accumulated = 0.;
+    for _ in 0..num_steps as u64 {
+        let mut jumped = distribution.clone();
+        let mut weight = (-mean_jumps).exp();
+        let mut cumulative_weight = weight;
+        let mut next = &jumped * weight;
+        // The step spends an expected `(1 - cumulative_weight) / rate` time units after exactly `n`
+        // jumps, and these weights sum to `mean_jumps` over all `n`.
+        let mut reward_weight_left = mean_jumps;
+        let mut step_reward = 0.;
+        let mut n = 0.;
+        loop {
+            if let Some(reward) = reward {
+                let reward_weight = 1. - cumulative_weight;
+                step_reward += jumped.dot(reward) * reward_weight;
+                reward_weight_left -= reward_weight;
+            }
+            let distribution_converged = 1. - cumulative_weight <= distribution_tolerance;
+            let reward_converged = reward_weight_left * reward_scale / rate <= reward_tolerance;
+            let past_rounding = n > mean_jumps && weight < f64::EPSILON * cumulative_weight;
+            if (distribution_converged && reward_converged) || past_rounding {
+                break;
+            }
+            n += 1.;
+            jumped = jump(&jumped);
+            weight *= mean_jumps / n;
+            cumulative_weight += weight;
+            next.scaled_add(weight, &jumped);
+        }
+        accumulated += step_reward / rate;
+        distribution = next;
+    }
+    (distribution, accumulated)
+}
+
+/// [`uniformize`] with a dense generator.
+fn uniformize_matrix(
+    generator: &Matrix,
+    distribution: &Vector,
+    reward: Option<&Vector>,
+    time: f64,
+    tolerance: f64,
+) -> (Vector, f64) {
+    assert_eq!(
+        distribution.len(),
+        generator.nrows(),
+        "Distribution must have one entry per state"
+    );
+    let rate = generator
+        .diag()
+        .iter()
+        .fold(0., |max: f64, rate| max.max(-rate));
+    let jump_matrix = if rate == 0. {
+        Matrix::eye(generator.nrows())
+    } else {
+        Matrix::eye(generator.nrows()) + generator / rate
+    };
+    let jump = |distribution: &Vector| distribution.dot(&jump_matrix);
+    uniformize(rate, jump, distribution, reward, time, tolerance)
+}
+
+/// Distribution at `time` of the CTMC with the given generator started from `initial`, computed
+/// by uniformization to within `tolerance` in total variation.
+///
+/// The generator's rows may sum to less than zero, see [`crate::MatrixTransitions`].
+pub fn transient_distribution(
+    generator: &Matrix,
+    initial: &Vector,
+    time: f64,
+    tolerance: f64,
+) -> Vector {
+    validate_subgenerator(generator);
+    uniformize_matrix(generator, initial, None, time, tolerance).0
+}
+
+/// Distributions at each of the increasing `times`, see [`transient_distribution`].
+pub fn transient_distributions(
+    generator: &Matrix,
+    initial: &Vector,
+    times: &[f64],
+    tolerance: f64,
+) -> Vec<Vector> {
+    validate_subgenerator(generator);
+    let step_tolerance = tolerance / times.len().max(1) as f64;
+    let mut distribution = initial.clone();
+    let mut time = 0.;
+    times
+        .iter()
+        .map(|&next_time| {
+            assert!(
+                next_time >= time,
+                "Times must be non-negative and increasing"
+            );
+            distribution = uniformize_matrix(
+                generator,
+                &distribution,
+                None,
+                next_time - time,
+                step_tolerance,
+            )
+            .0;
+            time = next_time;
+            distribution.clone()
+        })
+        .collect()
+}
+
+/// Expected reward accumulated over `[0, time]` by the CTMC with the given generator started from
+/// `initial`, where `reward` is the rate at which reward accrues in each state. Computed by
+/// uniformization to within `tolerance`.
+pub fn expected_accumulated_reward(
+    generator: &Matrix,
+    initial: &Vector,
+    reward: &Vector,
+    time: f64,
+    tolerance: f64,
+) -> f64 {
+    validate_subgenerator(generator);
+    assert_eq!(
+        reward.len(),
+        generator.nrows(),
+        "Reward must have one entry per state"
+    );
+    uniformize_matrix(generator, initial, Some(reward), time, tolerance).1
+}
+
+/// Coefficients of the degree 13 Padé approximant of the exponential.
+const PADE_13: [f64; 14] = [
+    64764752532480000.,
+    32382376266240000.,
+    7771770303897600.,
+    1187353796428800.,
+    129060195264000.,
+    10559470521600.,
+    670442572800.,
+    33522128640.,
+    1323241920.,
+    40840800.,
+    960960.,
+    16380.,
+    182.,
+    1.,
+];
+
+/// Largest 1-norm for which the degree 13 Padé approximant is accurate to double precision.
+const PADE_13_THETA: f64 = 5.371920351148152;
+
+/// Matrix exponential by Padé approximation with scaling and squaring (Higham, 2005).
+pub fn expm(matrix: &Matrix) -> Matrix {
+    assert!(matrix.is_square(), "Matrix must be square");
+    let norm = matrix
+        .columns()
+        .into_iter()
+        .map(|column| column.iter().map(|value| value.abs()).sum::<f64>())
+        .fold(0., f64::max);
+    let squarings = if norm > PADE_13_THETA {
+        (norm / PADE_13_THETA).log2().ceil() as i32
+    } else {
+        0
+    };
+    let a = matrix / 2f64.powi(squarings);
+    let identity = Matrix::eye(a.nrows());
+    let a2 = a.dot(&a);
+    let a4 = a2.dot(&a2);
+    let a6 = a4.dot(&a2);
+    let b = PADE_13;
+
+    let u_inner = a6.dot(&(&a6 * b[13] + &a4 * b[11] + &a2 * b[9]))
+        + &a6 * b[7]
+        + &a4 * b[5]
+        + &a2 * b[3]
+        + &identity * b[1];
+    let u = a.dot(&u_inner);
+    let v = a6.dot(&(&a6 * b[12] + &a4 * b[10] + &a2 * b[8]))
+        + &a6 * b[6]
+        + &a4 * b[4]
+        + &a2 * b[2]
+        + &identity * b[0];
+
+    let mut result = linalg::solve_matrix(&(&v - &u), &(&v + &u));
+    for _ in 0..squarings {
+        result = result.dot(&result);
+    }
+    result
+}
+
+/// Matrix of transition probabilities `P(X_time = j | X_0 = i)` of the CTMC with the given
+/// generator, computed with [`expm`].
+pub fn transition_probabilities(generator: &Matrix, time: f64) -> Matrix {
+    validate_subgenerator(generator);
+    assert!(time >= 0., "Time must be non-negative. Got {time}");
+    expm(&(generator * time))
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    const A: f64 = 3.;
+    const B: f64 = 5.;
+
+    fn two_state_generator() -> Matrix {
+        array![[-A, A], [B, -B]]
+    }
+
+    /// `P(t) = Π + e^{-(a+b)t} (I - Π)` with `Π` the matrix of stationary rows.
+    fn two_state_probabilities(time: f64) -> Matrix {
+        let limit = array![[B, A], [B, A]] / (A + B);
+        &limit + (Matrix::eye(2) - &limit) * (-(A + B) * time).exp()
+    }
+
+    fn distance(a: &Matrix, b: &Matrix) -> f64 {
+        (a - b).mapv(f64::abs).sum()
+    }
+
+    #[test]
+    fn expm_matches_two_state_closed_form() {
+        for time in [0.001, 0.1, 2., 50.] {
+            let probabilities = transition_probabilities(&two_state_generator(), time);
+            assert!(
+                distance(&probabilities, &two_state_probabilities(time)) < 1e-12,
+                "At time {time}"
+            );
+        }
+    }
+
+    #[test]
+    fn expm_scales_and_squares_large_norms() {
+        let exponential = expm(&array![[-30., 0.], [0., 2.]]);
+        let expected = array![[(-30f64).exp(), 0.], [0., 2f64.exp()]];
+        assert!(distance(&exponential, &expected) < 1e-12);
+    }
+
+    #[test]
+    fn uniformization_matches_two_state_closed_form() {
+        let tolerance = 1e-10;
+        // The last time needs several uniformization steps.
+        for time in [0.001, 0.1, 2., 200.] {
+            let distribution =
+                transient_distribution(&two_state_generator(), &array![1., 0.], time, tolerance);
+            let expected = two_state_probabilities(time).row(0).to_owned();
+            assert!(
+                (&distribution - &expected).mapv(f64::abs).sum() <= tolerance,
+                "At time {time}"
+            );
+        }
+    }
+
+    #[test]
+    fn uniformization_agrees_with_expm() {
+        let generator = array![
+            [-3., 1., 2., 0.],
+            [0., -1., 0.5, 0.5],
+            [4., 0., -5., 1.],
+            [0., 2., 0., -2.],
+        ];
+        let initial = array![0.25, 0., 0.5, 0.25];
+        for tolerance in [1e-4, 1e-8] {
+            let times = [0.5, 1., 4.];
+            let distributions = transient_distributions(&generator, &initial, &times, tolerance);
+            for (&time, distribution) in times.iter().zip(&distributions) {
+                let expected = initial.dot(&transition_probabilities(&generator, time));
+                assert!((distribution - &expected).mapv(f64::abs).sum() <= tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn accumulated_reward_matches_closed_form() {
+        // Expected time in state 0 from state 0: b t / (a + b) + a (1 - e^{-(a+b)t}) / (a + b)².
+        let tolerance = 1e-9;
+        for time in [0.1, 3., 100.] {
+            let reward = expected_accumulated_reward(
+                &two_state_generator(),
+                &array![1., 0.],
+                &array![1., 0.],
+                time,
+                tolerance,
+            );
+            let expected =
+                B * time / (A + B) + A * (1. - (-(A + B) * time).exp()) / ((A + B) * (A + B));
+            assert!((reward - expected).abs() <= tolerance, "At time {time}");
+        }
+    }
+}
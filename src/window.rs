@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+
+use crate::Sample;
+
+/// Calls `visit` with every state of a piecewise-constant path and the time it spends in that
+/// state during `[start, end]`.
+///
+/// The path is in `state` from `time` on and jumps as given by `next_jump`, which returns `None`
+/// once the path stays in its state forever. Jumps are pulled until the first one at or after
+/// `end`.
+fn for_each_sojourn<T: Clone>(
+    (mut time, mut state): (f64, T),
+    mut next_jump: impl FnMut() -> Option<(f64, T)>,
+    (start, end): (f64, f64),
+    mut visit: impl FnMut(T, f64),
+) {
+    assert!(
+        start >= time,
+        "Window starts at {start}, before the current time {time}"
+    );
+    assert!(start < end, "Window must have positive length.");
+    loop {
+        let next = next_jump();
+        let next_time = next
+            .as_ref()
+            .map_or(f64::INFINITY, |&(next_time, _)| next_time);
+        let duration = next_time.min(end) - time.max(start);
+        if duration > 0. {
+            visit(state.clone(), duration);
+        }
+        match next {
+            Some((next_time, next_state)) if next_time < end => {
+                time = next_time;
+                state = next_state;
+            }
+            _ => return,
+        }
+    }
+}
+
+/// Time average of `f` of the state over `[start, end]`, see [`for_each_sojourn`].
+pub(crate) fn time_average<T: Clone, S: Sample>(
+    position: (f64, T),
+    next_jump: impl FnMut() -> Option<(f64, T)>,
+    (start, end): (f64, f64),
+    f: impl Fn(T) -> S,
+) -> S {
+    let mut total: Option<S> = None;
+    for_each_sojourn(position, next_jump, (start, end), |state, duration| {
+        let value = f(state);
+        total = Some(match total.take() {
+            None => value.map_components(|value| value * duration),
+            Some(total) => total.zip_components(&value, |total, value| total + value * duration),
+        });
+    });
+    total
+        .expect("A window of positive length contains a sojourn.")
+        .map_components(|total| total / (end - start))
+}
+
+/// Fraction of `[start, end]` spent in each visited state, see [`for_each_sojourn`].
+pub(crate) fn occupation<T: Clone + Ord>(
+    position: (f64, T),
+    next_jump: impl FnMut() -> Option<(f64, T)>,
+    (start, end): (f64, f64),
+) -> BTreeMap<T, f64> {
+    let mut occupation = BTreeMap::new();
+    for_each_sojourn(position, next_jump, (start, end), |state, duration| {
+        *occupation.entry(state).or_insert(0.) += duration / (end - start);
+    });
+    occupation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path in state 0 from time 0, jumping to 1 at 1, to 2 at 2.5 and back to 0 at 4 for good.
+    struct Path {
+        jumps: Vec<(f64, u64)>,
+        pulled: usize,
+    }
+
+    impl Path {
+        fn new() -> Self {
+            Self {
+                jumps: vec![(1., 1), (2.5, 2), (4., 0)],
+                pulled: 0,
+            }
+        }
+
+        fn next_jump(&mut self) -> Option<(f64, u64)> {
+            self.pulled += 1;
+            self.jumps.get(self.pulled - 1).copied()
+        }
+
+        fn time_average(&mut self, window: (f64, f64)) -> f64 {
+            time_average((0., 0), || self.next_jump(), window, |state| state as f64)
+        }
+
+        fn occupation(&mut self, window: (f64, f64)) -> Vec<(u64, f64)> {
+            occupation((0., 0), || self.next_jump(), window)
+                .into_iter()
+                .collect()
+        }
+    }
+
+    #[test]
+    fn window_starting_and_ending_mid_sojourn() {
+        // Half a unit in 0, one and a half in 1 and half a unit in 2.
+        assert_eq!(Path::new().time_average((0.5, 3.)), 1.);
+        assert_eq!(
+            Path::new().occupation((0.5, 3.)),
+            [(0, 0.2), (1, 0.6), (2, 0.2)]
+        );
+    }
+
+    #[test]
+    fn window_without_jumps() {
+        assert_eq!(Path::new().time_average((1.5, 2.)), 1.);
+        assert_eq!(Path::new().occupation((1.5, 2.)), [(1, 1.)]);
+        // After the last jump the path stays put.
+        assert_eq!(Path::new().time_average((5., 7.)), 0.);
+        assert_eq!(Path::new().occupation((5., 7.)), [(0, 1.)]);
+    }
+
+    #[test]
+    fn window_ending_on_a_jump() {
+        // The state entered at the end of the window takes no time in it.
+        assert_eq!(Path::new().time_average((0.5, 2.5)), 0.75);
+        assert_eq!(Path::new().occupation((0.5, 2.5)), [(0, 0.25), (1, 0.75)]);
+    }
+
+    #[test]
+    fn jumps_are_pulled_up_to_the_first_at_or_after_the_end() {
+        for (window, pulled) in [
+            ((0.5, 3.), 3),
+            ((1.5, 2.), 2),
+            ((0.5, 2.5), 2),
+            ((0.5, 2.6), 3),
+            // The last jump is followed by `None`.
+            ((5., 7.), 4),
+        ] {
+            let mut path = Path::new();
+            path.time_average(window);
+            assert_eq!(path.pulled, pulled, "Window {window:?}");
+            let mut path = Path::new();
+            path.occupation(window);
+            assert_eq!(path.pulled, pulled, "Window {window:?}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "before the current time")]
+    fn window_before_the_current_time_panics() {
+        time_average((1., 0), || None, (0.5, 2.), |state: u64| state as f64);
+    }
+}
@@ -0,0 +1,352 @@
+use std::cell::Cell;
+
+use rand::Rng;
+use rand_distr::{Distribution, Exp};
+use rand_pcg::Pcg64Mcg;
+use stoc::{
+    BirthDeathAnalysis, EventLog, GeneralQueueSystem, MarkovQueueProbabilities,
+    MarkovServiceQueueSystem, QueueDiscipline, QueueEventKind, QueueSystem, StochasticProcess,
+};
+
+const WARMUP: f64 = 1_000.;
+const RUN_TIME: f64 = 200_000.;
+
+/// A service time that is always the same.
+struct Deterministic(f64);
+
+impl Distribution<f64> for Deterministic {
+    fn sample<R: Rng + ?Sized>(&self, _rng: &mut R) -> f64 {
+        self.0
+    }
+}
+
+/// Service times that repeat the given ones in order.
+struct Repeating(Vec<f64>, Cell<usize>);
+
+impl Distribution<f64> for Repeating {
+    fn sample<R: Rng + ?Sized>(&self, _rng: &mut R) -> f64 {
+        let index = self.1.get();
+        self.1.set((index + 1) % self.0.len());
+        self.0[index]
+    }
+}
+
+/// Long-run mean number of customers in the system.
+fn mean_length(queue: &mut impl QueueSystem, rng: &mut impl Rng) -> f64 {
+    queue.advance_to(WARMUP, rng);
+    queue.time_average(|length| length as f64, WARMUP, WARMUP + RUN_TIME, rng)
+}
+
+/// Asserts that a simulated long-run mean is within 3% of its exact value.
+fn assert_simulated_close(simulated: f64, expected: f64) {
+    assert!(
+        (simulated - expected).abs() < 0.03 * expected,
+        "Simulated {simulated}, expected {expected}"
+    );
+}
+
+#[test]
+fn mm1_mean_length() {
+    let (arrival_rate, service_rate) = (1., 2.);
+    let rho: f64 = arrival_rate / service_rate;
+    let expected = rho / (1. - rho);
+
+    let mut rng = Pcg64Mcg::new(1);
+    let mut queue = GeneralQueueSystem::new(
+        Some(1),
+        Exp::new(arrival_rate).unwrap(),
+        Exp::new(service_rate).unwrap(),
+        0,
+        &mut rng,
+    );
+    assert_simulated_close(mean_length(&mut queue, &mut rng), expected);
+
+    let mut queue = MarkovServiceQueueSystem::new(
+        Some(1),
+        Exp::new(arrival_rate).unwrap(),
+        service_rate,
+        0,
+        &mut rng,
+    );
+    assert_simulated_close(mean_length(&mut queue, &mut rng), expected);
+}
+
+#[test]
+fn mmc_matches_birth_death_analysis() {
+    // The analysis is checked against Erlang C in its unit tests.
+    let (num_units, arrival_rate, service_rate) = (3, 2.4, 1.);
+    let model = MarkovQueueProbabilities::new(arrival_rate, service_rate, num_units);
+    let expected = BirthDeathAnalysis::infinite(&model, 1e-12).unwrap().mean();
+
+    let mut rng = Pcg64Mcg::new(2);
+    let mut queue = GeneralQueueSystem::new(
+        Some(num_units),
+        Exp::new(arrival_rate).unwrap(),
+        Exp::new(service_rate).unwrap(),
+        0,
+        &mut rng,
+    );
+    assert_simulated_close(mean_length(&mut queue, &mut rng), expected);
+
+    let mut queue = MarkovServiceQueueSystem::new(
+        Some(num_units),
+        Exp::new(arrival_rate).unwrap(),
+        service_rate,
+        0,
+        &mut rng,
+    );
+    assert_simulated_close(mean_length(&mut queue, &mut rng), expected);
+}
+
+#[test]
+fn md1_matches_pollaczek_khinchine() {
+    let (arrival_rate, service_time) = (0.7, 1.);
+    let rho: f64 = arrival_rate * service_time;
+    let expected = rho + rho * rho / (2. * (1. - rho));
+
+    let mut rng = Pcg64Mcg::new(3);
+    let mut queue = GeneralQueueSystem::new(
+        Some(1),
+        Exp::new(arrival_rate).unwrap(),
+        Deterministic(service_time),
+        0,
+        &mut rng,
+    );
+    assert_simulated_close(mean_length(&mut queue, &mut rng), expected);
+}
+
+#[test]
+fn infinite_units_mean_length() {
+    let (arrival_rate, service_rate) = (3., 0.5);
+
+    let mut rng = Pcg64Mcg::new(4);
+    let mut queue = GeneralQueueSystem::new(
+        None,
+        Exp::new(arrival_rate).unwrap(),
+        Deterministic(1. / service_rate),
+        0,
+        &mut rng,
+    );
+    assert_simulated_close(
+        mean_length(&mut queue, &mut rng),
+        arrival_rate / service_rate,
+    );
+
+    let mut queue = MarkovServiceQueueSystem::new(
+        None,
+        Exp::new(arrival_rate).unwrap(),
+        service_rate,
+        0,
+        &mut rng,
+    );
+    assert_simulated_close(
+        mean_length(&mut queue, &mut rng),
+        arrival_rate / service_rate,
+    );
+}
+
+#[test]
+fn step_t_lands_on_the_requested_time() {
+    let mut rng = Pcg64Mcg::new(5);
+    let mut queue = MarkovServiceQueueSystem::new(Some(2), Exp::new(1.).unwrap(), 1., 4, &mut rng);
+    queue.step_t(2.5, &mut rng);
+    assert_eq!(queue.time(), 2.5);
+    assert_eq!(queue.queue_length(), queue.state());
+    assert_eq!(queue.num_units(), Some(2));
+}
+
+#[test]
+fn mm1_waiting_times_from_event_log() {
+    let (arrival_rate, service_rate) = (1., 2.);
+    let rho: f64 = arrival_rate / service_rate;
+
+    let mut rng = Pcg64Mcg::new(6);
+    let mut queue = GeneralQueueSystem::new(
+        Some(1),
+        Exp::new(arrival_rate).unwrap(),
+        Exp::new(service_rate).unwrap(),
+        0,
+        &mut rng,
+    )
+    .with_event_log();
+    let mean_length = mean_length(&mut queue, &mut rng);
+
+    let event_log = queue.event_log().unwrap();
+    let customers: Vec<_> = event_log
+        .departures_arrived_in(WARMUP, WARMUP + RUN_TIME - 100.)
+        .collect();
+    let mean_waiting_time = customers
+        .iter()
+        .map(|customer| customer.waiting_time().unwrap())
+        .sum::<f64>()
+        / customers.len() as f64;
+    let mean_sojourn_time = customers
+        .iter()
+        .map(|customer| customer.sojourn_time().unwrap())
+        .sum::<f64>()
+        / customers.len() as f64;
+    let waited = customers
+        .iter()
+        .filter(|customer| customer.waiting_time().unwrap() > 0.)
+        .count() as f64
+        / customers.len() as f64;
+
+    assert_simulated_close(mean_waiting_time, rho / (service_rate - arrival_rate));
+    assert_simulated_close(waited, rho);
+    // Little's law.
+    assert_simulated_close(mean_length, arrival_rate * mean_sojourn_time);
+
+    assert!(event_log
+        .events()
+        .windows(2)
+        .all(|pair| pair[0].time() <= pair[1].time()));
+    let departures = event_log
+        .events()
+        .iter()
+        .filter(|event| event.kind() == QueueEventKind::Departure)
+        .count();
+    assert_eq!(departures, event_log.departures().len());
+}
+
+/// Mean waiting time of the customers of `class` that arrived after the warm-up.
+fn mean_waiting_time(event_log: &EventLog, class: usize) -> f64 {
+    let waiting_times: Vec<f64> = event_log
+        .departures_arrived_in(WARMUP, WARMUP + RUN_TIME - 100.)
+        .filter(|customer| customer.class() == class)
+        .map(|customer| customer.waiting_time().unwrap())
+        .collect();
+    waiting_times.iter().sum::<f64>() / waiting_times.len() as f64
+}
+
+fn mm1_with_discipline(
+    discipline: QueueDiscipline,
+    seed: u128,
+) -> (GeneralQueueSystem<Exp<f64>, Exp<f64>>, Pcg64Mcg) {
+    let mut rng = Pcg64Mcg::new(seed);
+    let queue = GeneralQueueSystem::with_discipline(
+        Some(1),
+        Exp::new(1.).unwrap(),
+        Exp::new(2.).unwrap(),
+        0,
+        discipline,
+        &mut rng,
+    )
+    .with_event_log();
+    (queue, rng)
+}
+
+#[test]
+fn size_blind_disciplines_keep_mean_length() {
+    for (seed, discipline) in [
+        QueueDiscipline::Lifo,
+        QueueDiscipline::Priority {
+            class_probabilities: vec![0.5, 0.5],
+        },
+        QueueDiscipline::PreemptivePriority {
+            class_probabilities: vec![0.5, 0.5],
+        },
+        QueueDiscipline::ProcessorSharing,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let (mut queue, mut rng) = mm1_with_discipline(discipline, seed as u128 + 10);
+        assert_simulated_close(mean_length(&mut queue, &mut rng), 1.);
+    }
+}
+
+#[test]
+fn shortest_job_first_shortens_the_queue() {
+    let (mut queue, mut rng) = mm1_with_discipline(QueueDiscipline::ShortestJobFirst, 20);
+    assert!(mean_length(&mut queue, &mut rng) < 0.95);
+}
+
+#[test]
+fn lifo_keeps_mean_waiting_time() {
+    let (mut queue, mut rng) = mm1_with_discipline(QueueDiscipline::Lifo, 21);
+    mean_length(&mut queue, &mut rng);
+    assert_simulated_close(mean_waiting_time(queue.event_log().unwrap(), 0), 0.5);
+}
+
+#[test]
+fn priority_matches_cobham() {
+    // λ = 1 split evenly over two classes, μ = 2: the mean residual work is λ E[S²] / 2 = 1/4 and
+    // the loads are 1/4 per class.
+    let (mut queue, mut rng) = mm1_with_discipline(
+        QueueDiscipline::Priority {
+            class_probabilities: vec![0.5, 0.5],
+        },
+        22,
+    );
+    mean_length(&mut queue, &mut rng);
+    let event_log = queue.event_log().unwrap();
+    assert_simulated_close(mean_waiting_time(event_log, 0), 0.25 / (1. - 0.25));
+    assert_simulated_close(
+        mean_waiting_time(event_log, 1),
+        0.25 / ((1. - 0.25) * (1. - 0.5)),
+    );
+}
+
+#[test]
+fn preemptive_priority_hides_lower_classes() {
+    // The highest class sees an M/M/1 queue of its own.
+    let (mut queue, mut rng) = mm1_with_discipline(
+        QueueDiscipline::PreemptivePriority {
+            class_probabilities: vec![0.5, 0.5],
+        },
+        23,
+    );
+    mean_length(&mut queue, &mut rng);
+    let event_log = queue.event_log().unwrap();
+    let sojourn_times: Vec<f64> = event_log
+        .departures_arrived_in(WARMUP, WARMUP + RUN_TIME - 100.)
+        .filter(|customer| customer.class() == 0)
+        .map(|customer| customer.sojourn_time().unwrap())
+        .collect();
+    let mean_sojourn_time = sojourn_times.iter().sum::<f64>() / sojourn_times.len() as f64;
+    assert_simulated_close(mean_sojourn_time, 1. / (2. - 0.5));
+    assert!(event_log
+        .events()
+        .iter()
+        .any(|event| event.kind() == QueueEventKind::Preemption));
+}
+
+#[test]
+fn processor_sharing_is_insensitive() {
+    // M/D/1 under processor sharing has the mean length of M/M/1, unlike under FIFO.
+    let (arrival_rate, service_time) = (0.5, 1.);
+    let mut rng = Pcg64Mcg::new(24);
+    let mut queue = GeneralQueueSystem::with_discipline(
+        Some(1),
+        Exp::new(arrival_rate).unwrap(),
+        Deterministic(service_time),
+        0,
+        QueueDiscipline::ProcessorSharing,
+        &mut rng,
+    );
+    assert_simulated_close(mean_length(&mut queue, &mut rng), 1.);
+}
+
+#[test]
+fn processor_sharing_departure_times() {
+    // Two customers share one unit until the shorter one leaves, then the other is served alone.
+    let mut rng = Pcg64Mcg::new(25);
+    let mut queue = GeneralQueueSystem::with_discipline(
+        Some(1),
+        Deterministic(10.),
+        Repeating(vec![1., 3., 0.5], Cell::new(0)),
+        2,
+        QueueDiscipline::ProcessorSharing,
+        &mut rng,
+    )
+    .with_event_log();
+    queue.advance_to(12., &mut rng);
+    let departures: Vec<_> = queue
+        .event_log()
+        .unwrap()
+        .departures()
+        .iter()
+        .map(|customer| (customer.id(), customer.departure_time().unwrap()))
+        .collect();
+    assert_eq!(departures, [(0, 2.), (1, 4.), (2, 10.5)]);
+}